@@ -0,0 +1,201 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use fso_tables_impl::curves::{BUILTIN_CURVES, CurveSegment, CurveTable};
+use fso_tables_impl::FSOTableFileParser;
+use crate::context_bar::{find_curve_tables, is_curve_table, load_order_key};
+use crate::dependencies::DependencyGraph;
+use crate::note_bar::NoteSeverity;
+use crate::plot_panel::{effective_curves, KEYFRAME_MIN_X_DISTANCE};
+
+const CHECK_USAGE: &str = "Usage: curvedit check [--json] <table, tables directory or mod folder>...";
+
+//Release builds on Windows start without a console of their own, so the output goes to the one of the terminal we were started from.
+#[cfg(windows)]
+fn attach_parent_console() {
+	const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+	#[link(name = "kernel32")]
+	extern "system" {
+		fn AttachConsole(process_id: u32) -> i32;
+	}
+	unsafe {
+		AttachConsole(ATTACH_PARENT_PROCESS);
+	}
+}
+
+struct Problem {
+	severity: NoteSeverity,
+	file: PathBuf,
+	line: Option<usize>,
+	message: String
+}
+
+//Returns the process exit code: 0 if all tables are fine, 1 if there were errors, 2 on invalid usage
+pub(crate) fn check_tables(args: impl Iterator<Item = String>) -> i32 {
+	#[cfg(windows)]
+	attach_parent_console();
+
+	let mut json = false;
+	let mut paths: Vec<PathBuf> = Vec::new();
+	for arg in args {
+		match arg.as_str() {
+			"--json" => { json = true; }
+			"--help" | "-h" => {
+				println!("{}", CHECK_USAGE);
+				return 0;
+			}
+			_ => { paths.push(PathBuf::from(arg)); }
+		}
+	}
+
+	if paths.is_empty() {
+		eprintln!("{}", CHECK_USAGE);
+		return 2;
+	}
+
+	let mut problems: Vec<Problem> = Vec::new();
+	let mut files: Vec<PathBuf> = Vec::new();
+	for path in paths {
+		if path.is_dir() {
+			let mut failures: Vec<String> = Vec::new();
+			find_curve_tables(&path, &mut files, &mut failures);
+			for failure in failures {
+				problems.push(Problem { severity: NoteSeverity::Error, file: path.clone(), line: None, message: failure });
+			}
+		}
+		else if is_curve_table(&path) {
+			files.push(path);
+		}
+		else {
+			problems.push(Problem { severity: NoteSeverity::Error, file: path, line: None, message: "Not a curves table".to_string() });
+		}
+	}
+
+	//Check in the order FSO loads the tables, so overrides are resolved the same way
+	files.sort_by_cached_key(|file| load_order_key(file));
+	files.dedup();
+
+	let mut tables: Vec<(CurveTable, PathBuf)> = Vec::new();
+	for file in files.iter() {
+		match FSOTableFileParser::new(file).and_then(CurveTable::parse) {
+			Ok(table) => { tables.push((table, file.clone())); }
+			Err(error) => {
+				problems.push(Problem { severity: NoteSeverity::Error, file: file.clone(), line: Some(error.line), message: error.reason.to_string() });
+			}
+		}
+	}
+
+	check_semantics(&tables, &mut problems);
+
+	let num_errors = problems.iter().filter(|problem| problem.severity == NoteSeverity::Error).count();
+	if json {
+		println!("{}", problems_to_json(&problems, files.len(), num_errors == 0));
+	}
+	else {
+		for problem in problems.iter() {
+			let location = match problem.line {
+				Some(line) => format!("{}:{}", problem.file.to_string_lossy(), line),
+				None => problem.file.to_string_lossy().to_string()
+			};
+			eprintln!("{}: {}: {}", location, severity_name(&problem.severity), problem.message);
+		}
+		let num_warnings = problems.iter().filter(|problem| problem.severity == NoteSeverity::Warning).count();
+		println!("Checked {} table(s): {} error(s), {} warning(s).", files.len(), num_errors, num_warnings);
+	}
+
+	if num_errors > 0 { 1 } else { 0 }
+}
+
+fn check_semantics(tables: &[(CurveTable, PathBuf)], problems: &mut Vec<Problem>) {
+	let available_curves = effective_curves(tables.iter().flat_map(|(table, _)| table.curves.iter()));
+	let dependencies = DependencyGraph::new(&available_curves);
+	let mut seen_names: Vec<(&str, &Path)> = Vec::new();
+	for (table, file) in tables.iter() {
+		let content = fs::read_to_string(file).unwrap_or_default();
+		for curve in table.curves.iter() {
+			let line = find_curve_line(&content, &curve.name);
+
+			if BUILTIN_CURVES.iter().any(|builtin| builtin.name == curve.name) {
+				problems.push(Problem { severity: NoteSeverity::Error, file: file.clone(), line, message: format!("Curve {} has the same name as a builtin curve", curve.name) });
+			}
+			else if let Some((_, other_file)) = seen_names.iter().rev().find(|(name, _)| *name == curve.name) {
+				if *other_file == file.as_path() {
+					problems.push(Problem { severity: NoteSeverity::Error, file: file.clone(), line, message: format!("Curve {} is defined more than once in this table", curve.name) });
+				}
+				else {
+					problems.push(Problem { severity: NoteSeverity::Info, file: file.clone(), line, message: format!("Curve {} overrides the definition in {}", curve.name, other_file.to_string_lossy()) });
+				}
+			}
+			seen_names.push((curve.name.as_str(), file.as_path()));
+
+			if dependencies.is_on_cycle(&curve.name) {
+				problems.push(Problem { severity: NoteSeverity::Error, file: file.clone(), line, message: format!("Curve {} has cyclic subcurve references", curve.name) });
+			}
+			if curve.keyframes.len() < 2 {
+				problems.push(Problem { severity: NoteSeverity::Warning, file: file.clone(), line, message: format!("Curve {} has fewer than two keyframes", curve.name) });
+			}
+			if curve.keyframes.windows(2).any(|pair| pair[1].pos.0 - pair[0].pos.0 < KEYFRAME_MIN_X_DISTANCE) {
+				problems.push(Problem { severity: NoteSeverity::Error, file: file.clone(), line, message: format!("Keyframes of curve {} are not in ascending X order", curve.name) });
+			}
+
+			for (kf_num, keyframe) in curve.keyframes.iter().enumerate() {
+				match &keyframe.segment {
+					CurveSegment::Subcurve { curve: subcurve } if *subcurve == curve.name => {
+						problems.push(Problem { severity: NoteSeverity::Error, file: file.clone(), line, message: format!("Keyframe {} of curve {} uses itself as a subcurve", kf_num + 1, curve.name) });
+					}
+					CurveSegment::Subcurve { curve: subcurve } => {
+						let exists = BUILTIN_CURVES.iter().any(|other| other.name == *subcurve) || tables.iter().any(|(table, _)| table.curves.iter().any(|other| other.name == *subcurve));
+						if !exists {
+							problems.push(Problem { severity: NoteSeverity::Error, file: file.clone(), line, message: format!("Keyframe {} of curve {} references missing subcurve {}", kf_num + 1, curve.name, subcurve) });
+						}
+					}
+					CurveSegment::Polynomial { degree, .. } if *degree <= 0f32 => {
+						problems.push(Problem { severity: NoteSeverity::Error, file: file.clone(), line, message: format!("Keyframe {} of curve {} has a non-positive polynomial degree", kf_num + 1, curve.name) });
+					}
+					_ => {}
+				}
+			}
+		}
+	}
+}
+
+//The parsed table doesn't keep line numbers, so look up where the curve is declared
+fn find_curve_line(content: &str, name: &str) -> Option<usize> {
+	content.lines().position(|line| {
+		line.trim().strip_prefix("$Name:").is_some_and(|line_name| line_name.trim() == name)
+	}).map(|line| line + 1)
+}
+
+fn severity_name(severity: &NoteSeverity) -> &'static str {
+	match severity {
+		NoteSeverity::Info => "info",
+		NoteSeverity::Warning => "warning",
+		NoteSeverity::Error => "error"
+	}
+}
+
+fn problems_to_json(problems: &[Problem], num_files: usize, ok: bool) -> String {
+	let problems = problems.iter().map(|problem| {
+		format!("{{\"severity\":\"{}\",\"file\":\"{}\",\"line\":{},\"message\":\"{}\"}}",
+			severity_name(&problem.severity),
+			json_escape(&problem.file.to_string_lossy()),
+			problem.line.map_or("null".to_string(), |line| line.to_string()),
+			json_escape(&problem.message))
+	}).collect::<Vec<String>>();
+	format!("{{\"ok\":{},\"files\":{},\"problems\":[{}]}}", ok, num_files, problems.join(","))
+}
+
+fn json_escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\t' => escaped.push_str("\\t"),
+			c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+			c => escaped.push(c)
+		}
+	}
+	escaped
+}
@@ -0,0 +1,182 @@
+use fso_tables_impl::curves::{BUILTIN_CURVES, Curve, CurveTable};
+use crate::CurvEdit;
+use crate::context_bar::parse_table_text;
+use crate::dependencies::{DependencyGraph, subcurves_of};
+use crate::note_bar::{Note, NoteSeverity};
+use crate::plot_panel::{get_available_curves, KEYFRAME_MIN_X_DISTANCE, name_taken};
+use crate::selection::selected_in_curve;
+
+const CLIPBOARD_FILE_NAME: &str = "clipboard-crv.tbm";
+
+impl CurvEdit {
+	//Copies the selected keyframes if there are any, otherwise all shown curves
+	pub(crate) fn copy(&mut self, ctx: &egui::Context) {
+		if !self.selected_keyframes.is_empty() {
+			self.copy_selected_keyframes(ctx);
+		}
+		else {
+			self.copy_shown_curves(ctx);
+		}
+	}
+
+	pub(crate) fn copy_selected_keyframes(&mut self, ctx: &egui::Context) {
+		let mut curves: Vec<(usize, usize)> = Vec::new();
+		for (table, curve, _) in self.selected_keyframes.iter() {
+			if !curves.contains(&(*table, *curve)) {
+				curves.push((*table, *curve));
+			}
+		}
+
+		let copied = curves.iter().map(|(table, curve)| {
+			let source = &self.tables[*table].0.curves[*curve];
+			let keyframes = selected_in_curve(&self.selected_keyframes, (*table, *curve)).into_iter().map(|keyframe| source.keyframes[keyframe].clone()).collect();
+			Curve::new(source.name.clone(), keyframes)
+		}).collect::<Vec<Curve>>();
+		self.copy_curves(ctx, copied);
+	}
+
+	pub(crate) fn copy_shown_curves(&mut self, ctx: &egui::Context) {
+		let copied = self.curves_to_show.iter().map(|(table, curve)| self.tables[*table].0.curves[*curve].clone()).collect::<Vec<Curve>>();
+		self.copy_curves(ctx, copied);
+	}
+
+	fn copy_curves(&mut self, ctx: &egui::Context, curves: Vec<Curve>) {
+		if curves.is_empty() {
+			return;
+		}
+		let text = CurveTable::new(curves).spew();
+		ctx.output_mut(|output| output.copied_text = text);
+	}
+
+	//A single pasted curve goes into the curve of the selected keyframe unless asked for new curves, anything else is added as new curves
+	pub(crate) fn paste(&mut self, text: &str, as_new_curves: bool) {
+		let table = match parse_clipboard(text) {
+			Ok(table) if !table.curves.is_empty() => table,
+			Ok(_) => {
+				self.notes.push((Note {
+					text: "Cannot paste: Clipboard does not contain any curves!".to_string(),
+					severity: NoteSeverity::Error,
+					timeout: 5f32
+				}, None));
+				return;
+			}
+			Err(reason) => {
+				self.notes.push((Note {
+					text: format!("Cannot paste: {}!", reason),
+					severity: NoteSeverity::Error,
+					timeout: 5f32
+				}, None));
+				return;
+			}
+		};
+
+		match self.selected_keyframes.last().copied() {
+			Some((table_num, curve_num, _)) if table.curves.len() == 1 && !as_new_curves => {
+				let pasted = table.curves.into_iter().next().unwrap();
+				self.paste_keyframes(table_num, curve_num, pasted);
+			}
+			_ => { self.paste_curves(table.curves); }
+		}
+	}
+
+	//Pasted keyframes replace the keyframes of the target curve that lie within their X range
+	fn paste_keyframes(&mut self, table_num: usize, curve_num: usize, pasted: Curve) {
+		let target = &self.tables[table_num].0.curves[curve_num];
+		if self.tables[table_num].1.read_only {
+			self.notes.push((Note {
+				text: format!("Cannot paste into {}: It is part of a read-only reference table!", target.name),
+				severity: NoteSeverity::Error,
+				timeout: 5f32
+			}, None));
+			return;
+		}
+		let (Some(first), Some(last)) = (pasted.keyframes.first(), pasted.keyframes.last()) else { return; };
+		let (start, end) = (first.pos.0, last.pos.0);
+
+		if pasted.keyframes.windows(2).any(|pair| pair[1].pos.0 - pair[0].pos.0 < KEYFRAME_MIN_X_DISTANCE) {
+			self.notes.push((Note {
+				text: "Cannot paste: Pasted keyframes are not in ascending X order!".to_string(),
+				severity: NoteSeverity::Error,
+				timeout: 5f32
+			}, None));
+			return;
+		}
+
+		let dependencies = DependencyGraph::new(&get_available_curves(&self.tables));
+		if let Some(subcurve) = subcurves_of(&pasted).find(|subcurve| dependencies.would_create_cycle(&target.name, subcurve)) {
+			self.notes.push((Note {
+				text: format!("Cannot paste into {}: Using {} as a subcurve would create a cycle!", target.name, subcurve),
+				severity: NoteSeverity::Error,
+				timeout: 5f32
+			}, None));
+			return;
+		}
+
+		let before = target.keyframes.iter().filter(|keyframe| keyframe.pos.0 <= start - KEYFRAME_MIN_X_DISTANCE).cloned();
+		let after = target.keyframes.iter().filter(|keyframe| keyframe.pos.0 >= end + KEYFRAME_MIN_X_DISTANCE).cloned();
+		let mut keyframes = before.collect::<Vec<_>>();
+		let first_pasted = keyframes.len();
+		let num_pasted = pasted.keyframes.len();
+		keyframes.extend(pasted.keyframes);
+		keyframes.extend(after);
+
+		let target_name = target.name.clone();
+		let (table, file_data) = &mut self.tables[table_num];
+		table.curves[curve_num].keyframes = keyframes;
+		file_data.dirty = true;
+
+		self.selected_keyframes = (first_pasted..first_pasted + num_pasted).map(|keyframe| (table_num, curve_num, keyframe)).collect();
+		self.notes.push((Note {
+			text: format!("Pasted {} keyframe(s) into {}.", num_pasted, target_name),
+			severity: NoteSeverity::Info,
+			timeout: 3f32
+		}, None));
+	}
+
+	fn paste_curves(&mut self, curves: Vec<Curve>) {
+		let Some(table_num) = self.current_table() else {
+			self.notes.push((Note {
+				text: "Cannot paste curves: Open or create a table to paste them into first!".to_string(),
+				severity: NoteSeverity::Error,
+				timeout: 5f32
+			}, None));
+			return;
+		};
+
+		let mut pasted_names: Vec<String> = Vec::new();
+		for mut curve in curves {
+			//Pasting into a table that loads later overrides the curve, anything else gets a name of its own
+			if name_taken(&self.tables, table_num, &curve.name) {
+				let is_used = |name: &str| BUILTIN_CURVES.iter().any(|other| other.name == name) || self.tables.iter().any(|(table, _)| table.curves.iter().any(|other| other.name == name));
+				let new_name = (2..).map(|num| format!("{}_{}", curve.name, num)).find(|name| !is_used(name)).unwrap();
+				curve.name = new_name;
+			}
+			let dependencies = DependencyGraph::new(&get_available_curves(&self.tables));
+			if let Some(subcurve) = subcurves_of(&curve).find(|subcurve| dependencies.would_create_cycle(&curve.name, subcurve)) {
+				self.notes.push((Note {
+					text: format!("Cannot add {}: Using {} as a subcurve would create a cycle!", curve.name, subcurve),
+					severity: NoteSeverity::Error,
+					timeout: 5f32
+				}, None));
+				continue;
+			}
+			pasted_names.push(curve.name.clone());
+			let (table, file_data) = &mut self.tables[table_num];
+			file_data.dirty = true;
+			self.curves_to_show.push((table_num, table.curves.len()));
+			table.curves.push(curve);
+		}
+
+		if !pasted_names.is_empty() {
+			self.notes.push((Note {
+				text: format!("Pasted {} into {}.", pasted_names.join(", "), self.tables[table_num].1.file.file_name().unwrap_or("".as_ref()).to_string_lossy()),
+				severity: NoteSeverity::Info,
+				timeout: 3f32
+			}, None));
+		}
+	}
+}
+
+fn parse_clipboard(text: &str) -> Result<CurveTable, String> {
+	parse_table_text(text.as_bytes(), CLIPBOARD_FILE_NAME).map_err(|error| format!("Clipboard is not a valid curve table, {}", error))
+}
@@ -0,0 +1,81 @@
+use egui::{Align2, Color32, Id, Order, Sense, Vec2, ViewportCommand};
+use crate::CurvEdit;
+
+enum CloseAction {
+	Cancel,
+	SaveAll,
+	Discard
+}
+
+impl CurvEdit {
+	//Closing the window would silently drop unsaved tables, so hold it until the user decided what to do with them
+	pub(crate) fn intercept_close(&mut self, ctx: &egui::Context) {
+		if ctx.input(|i| i.viewport().close_requested()) && !self.allow_close && self.tables.iter().any(|(_, file_data)| file_data.dirty) {
+			ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+			self.close_dialog = true;
+		}
+	}
+
+	pub(crate) fn unsaved_tables_dialog(&mut self, ctx: &egui::Context) {
+		if !self.close_dialog {
+			return;
+		}
+
+		//Sits above the panels and below the dialog, swallowing all clicks meant for the editor
+		egui::Area::new(Id::new("close_dialog_backdrop"))
+			.order(Order::PanelResizeLine)
+			.fixed_pos(ctx.screen_rect().min)
+			.show(ctx, |ui| {
+				let screen = ctx.screen_rect();
+				ui.allocate_rect(screen, Sense::click_and_drag());
+				ui.painter().rect_filled(screen, 0f32, Color32::from_black_alpha(128));
+			});
+
+		let mut action: Option<CloseAction> = None;
+		egui::Window::new("Unsaved changes")
+			.collapsible(false)
+			.resizable(false)
+			.anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+			.show(ctx, |ui| {
+				ui.label("The following tables have unsaved changes:");
+				egui::ScrollArea::vertical().max_height(200f32).show(ui, |ui| {
+					for (_, file_data) in self.tables.iter().filter(|(_, file_data)| file_data.dirty) {
+						ui.label(file_data.file.to_string_lossy());
+					}
+				});
+				ui.separator();
+				ui.horizontal(|ui| {
+					if ui.button("Cancel").clicked() {
+						action = Some(CloseAction::Cancel);
+					}
+					if ui.button("Save All").on_hover_text("Save all changed tables, then quit.").clicked() {
+						action = Some(CloseAction::SaveAll);
+					}
+					if ui.button("Discard").on_hover_text("Quit without saving.").clicked() {
+						action = Some(CloseAction::Discard);
+					}
+				});
+			});
+
+		match action {
+			None => {}
+			Some(CloseAction::Cancel) => {
+				self.close_dialog = false;
+			}
+			Some(CloseAction::SaveAll) => {
+				self.save_all_tables();
+				//If anything failed to save, the errors show up in the note bar and the dialog stays open
+				if self.tables.iter().all(|(_, file_data)| !file_data.dirty) {
+					self.close_dialog = false;
+					self.allow_close = true;
+					ctx.send_viewport_cmd(ViewportCommand::Close);
+				}
+			}
+			Some(CloseAction::Discard) => {
+				self.close_dialog = false;
+				self.allow_close = true;
+				ctx.send_viewport_cmd(ViewportCommand::Close);
+			}
+		}
+	}
+}
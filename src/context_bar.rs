@@ -1,12 +1,18 @@
 use std::fs;
-use std::path::PathBuf;
-use egui::{menu, Ui};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use egui::{Key, KeyboardShortcut, menu, Modifiers, Ui, ViewportCommand};
 use fso_tables_impl::curves::CurveTable;
 use fso_tables_impl::FSOTableFileParser;
 use native_dialog::FileDialog;
 use crate::{CurvEdit, TableData};
+use crate::disk_watch::modified_time;
+use crate::history::{REDO_SHORTCUT, UNDO_SHORTCUT};
+use crate::CURVEDIT_VERSION;
 use crate::note_bar::{Note, NoteSeverity};
-use crate::plot_panel::get_available_curves;
+
+pub(crate) const SAVE_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::S);
+pub(crate) const SAVE_AS_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::S);
 
 impl CurvEdit {
 	pub(crate) fn context_bar(&mut self, ui: &mut Ui) {
@@ -23,21 +29,23 @@ impl CurvEdit {
 						self.try_open_file(path);
 					}
 				}
-				if ui.button("Open Tables Directory").clicked() {
+				if ui.button("Open Tables Directory").on_hover_text("Open all curve tables of a mod folder or a tables folder.").clicked() {
 					let path = FileDialog::new()
 						.set_location(self.tables.last().and_then(|table| table.1.file.parent()).unwrap_or(&self.default_path))
 						.show_open_single_dir();
 					if let Ok(Some(path)) = path {
-						if let Ok(files) = fs::read_dir(path){
-							for file in files {
-								if let Ok(file) = file {
-									let filename = file.file_name().to_string_lossy().to_ascii_lowercase();
-									if filename == "curves.tbl" || filename.ends_with("-crv.tbm") {
-										self.try_open_file(file.path());
-									}
-								}							
-							}
-						}
+						self.remember_recent(&path, true);
+						self.import_directory(&path, false);
+					}
+				}
+				self.recent_menu(ui);
+				if ui.button("Open VP Archive").on_hover_text("List the curve tables inside a VP archive.").clicked() {
+					let path = FileDialog::new()
+						.set_location(self.tables.last().and_then(|table| table.1.file.parent()).unwrap_or(&self.default_path))
+						.add_filter("VP Archive", &["vp"])
+						.show_open_single_file();
+					if let Ok(Some(path)) = path {
+						self.open_vp_archive(path);
 					}
 				}
 				if ui.button("New Table").clicked() {
@@ -46,19 +54,13 @@ impl CurvEdit {
 						.set_filename("-crv.tbm")
 						.add_filter("FSO Table", &["tbl", "tbm"])
 						.show_save_single_file();
-					if let Ok(Some(mut path)) = path {
-						let filename = path.file_name().unwrap_or("curves.tbl".as_ref()).to_string_lossy().to_ascii_lowercase();
-						if filename != "curves.tbl" && !filename.ends_with("-crv.tbm") {
-							path.set_file_name(path.file_name().map(|filename| {
-								let mut fname = filename.to_os_string();
-								fname.push("-crv.tbm");
-								fname
-							}).unwrap());
-						}
+					if let Ok(Some(path)) = path {
+						let path = with_table_name(path);
 						
 						match fs::write(&path, "") {
 							Ok(_) => {
-								self.tables.push((CurveTable::new(vec![]), TableData { file: path, dirty: true }));
+								self.tables.push((CurveTable::new(vec![]), TableData { modified: modified_time(&path), file: path, dirty: true, ..Default::default() }));
+								self.sort_tables();
 							}
 							Err(error) => {
 								self.notes.push((Note {
@@ -70,47 +72,345 @@ impl CurvEdit {
 						}
 					}
 				}
+				ui.separator();
+				if ui.button("Add Reference Directory").on_hover_text("Load all tables in a directory as read-only references.").clicked() {
+					let path = FileDialog::new()
+						.set_location(self.tables.last().and_then(|table| table.1.file.parent()).unwrap_or(&self.default_path))
+						.show_open_single_dir();
+					if let Ok(Some(path)) = path {
+						self.add_reference_directory(&path);
+					}
+				}
+				if ui.button("Add References from mod.ini").on_hover_text("Load the tables of all dependencies of a mod as read-only references.").clicked() {
+					let path = FileDialog::new()
+						.set_location(self.tables.last().and_then(|table| table.1.file.parent()).unwrap_or(&self.default_path))
+						.set_filename("mod.ini")
+						.add_filter("Mod Settings", &["ini"])
+						.show_open_single_file();
+					if let Ok(Some(path)) = path {
+						self.add_mod_ini_references(&path);
+					}
+				}
+				ui.separator();
+				let current_table = self.current_table();
+				if ui.add_enabled(current_table.is_some(), egui::Button::new("Save").shortcut_text(ui.ctx().format_shortcut(&SAVE_SHORTCUT))).clicked() {
+					self.save_current_table();
+					ui.close_menu();
+				}
+				if ui.add_enabled(current_table.is_some(), egui::Button::new("Save As").shortcut_text(ui.ctx().format_shortcut(&SAVE_AS_SHORTCUT))).clicked() {
+					self.save_current_table_as();
+					ui.close_menu();
+				}
+				if ui.add_enabled(self.tables.iter().any(|(_, file_data)| file_data.dirty), egui::Button::new("Save All")).clicked() {
+					self.save_all_tables();
+					ui.close_menu();
+				}
+			});
+			ui.menu_button("Edit", |ui| {
+				if ui.add_enabled(self.history.can_undo(), egui::Button::new("Undo").shortcut_text(ui.ctx().format_shortcut(&UNDO_SHORTCUT))).clicked() {
+					self.undo();
+				}
+				if ui.add_enabled(self.history.can_redo(), egui::Button::new("Redo").shortcut_text(ui.ctx().format_shortcut(&REDO_SHORTCUT))).clicked() {
+					self.redo();
+				}
+				ui.separator();
+				if ui.add_enabled(!self.selected_keyframes.is_empty(), egui::Button::new("Copy Keyframes")).clicked() {
+					self.copy_selected_keyframes(ui.ctx());
+					ui.close_menu();
+				}
+				if ui.add_enabled(!self.curves_to_show.is_empty(), egui::Button::new("Copy Shown Curves")).clicked() {
+					self.copy_shown_curves(ui.ctx());
+					ui.close_menu();
+				}
+				ui.separator();
+				if ui.add_enabled(self.selected_curve().is_some(), egui::Button::new("Keyframe Table")).on_hover_text("Edit all keyframes of the selected curve as a table.").clicked() {
+					self.open_keyframe_grid();
+					ui.close_menu();
+				}
 			});
 		});
 		ui.add_space(1f32);
 	}
 	
-	fn try_open_file(&mut self, path: PathBuf) {
-		if path.file_name().is_some_and(|path| {
-			let path = path.to_string_lossy().to_ascii_lowercase();
-			path == "curves.tbl" || path.ends_with("-crv.tbm")
-		}) {
-			let table_parse = FSOTableFileParser::new(&path).and_then(|parser| CurveTable::parse(parser));
-			match table_parse {
-				Ok (table) => {
-					let available_curves = get_available_curves(&self.tables);
-					let name_collision = table.curves.iter().find(|curve| available_curves.iter().find(|other| other.name == curve.name).is_some());
-					if let Some(curve) = name_collision {
-						self.notes.push((Note {
-							text: format!("Cannot add table {}, a curve with the name {} already exists!", path.file_name().unwrap_or("".as_ref()).to_string_lossy(), curve.name),
-							severity: NoteSeverity::Error,
-							timeout: 5f32
-						}, None));
-					}
-					else {
-						self.tables.push((table, TableData { file: path, dirty: false }));
-					}
-				}
-				Err (error) => {
-					self.notes.push((Note {
-						text: format!("Failed to parse {} at line {}: {}!", path.file_name().unwrap_or("".as_ref()).to_string_lossy(), error.line, error.reason),
-						severity: NoteSeverity::Error,
-						timeout: 5f32
-					}, None));
+	//The table that file operations act on: The one being edited, else the one being looked at, else the newest one. Reference tables are never acted on.
+	pub(crate) fn current_table(&self) -> Option<usize> {
+		let editable = |table: &usize| !self.tables[*table].1.read_only;
+		self.selected_keyframes.last().map(|(table, _, _)| *table).filter(editable)
+			.or(self.curves_to_show.iter().map(|(table, _)| *table).find(editable))
+			.or((0..self.tables.len()).rev().find(editable))
+	}
+
+	//Keeps the tables in the order FSO loads them in, which decides what overrides what
+	pub(crate) fn sort_tables(&mut self) {
+		//FSO loads curves.tbl first and then all modular tables alphabetically, no matter which mod they come from.
+		//A file of the same name in our mod replaces the one of a mod we depend on, so of those the reference goes first and is overridden.
+		self.preserving_selection(|curvedit| curvedit.tables.sort_by_cached_key(|(_, file_data)| {
+			let (not_base_table, filename, path) = load_order_key(&file_data.file);
+			(not_base_table, filename, !file_data.read_only, path)
+		}));
+	}
+
+	pub(crate) fn save_current_table(&mut self) {
+		if let Some(table_num) = self.current_table() {
+			let (table, file_data) = &mut self.tables[table_num];
+			save_table(table, file_data, &mut self.notes);
+		}
+	}
+
+	pub(crate) fn save_all_tables(&mut self) {
+		for (table, file_data) in self.tables.iter_mut().filter(|(_, file_data)| file_data.dirty) {
+			save_table(table, file_data, &mut self.notes);
+		}
+	}
+
+	pub(crate) fn save_current_table_as(&mut self) {
+		let Some(table_num) = self.current_table() else { return; };
+		let path = FileDialog::new()
+			.set_location(&self.tables[table_num].1.file)
+			.set_filename(&self.tables[table_num].1.file.file_name().unwrap_or("curves.tbl".as_ref()).to_string_lossy())
+			.add_filter("FSO Table", &["tbl", "tbm"])
+			.show_save_single_file();
+		let Ok(Some(path)) = path else { return; };
+		let path = with_table_name(path);
+
+		if path != self.tables[table_num].1.file && self.tables.iter().any(|(_, file_data)| file_data.file == path) {
+			self.notes.push((Note {
+				text: format!("Cannot save as {}: A table with this file is already open!", path.file_name().unwrap_or("".as_ref()).to_string_lossy()),
+				severity: NoteSeverity::Error,
+				timeout: 5f32
+			}, None));
+			return;
+		}
+
+		let old_path = self.tables[table_num].1.file.clone();
+		let (table, file_data) = &mut self.tables[table_num];
+		let mut new_file_data = TableData { file: path, dirty: true, ..Default::default() };
+		if save_table(table, &mut new_file_data, &mut self.notes) {
+			*file_data = new_file_data;
+			self.history.file_renamed(&old_path, &file_data.file);
+			self.sort_tables();
+		}
+	}
+
+	pub(crate) fn update_window_title(&mut self, ctx: &egui::Context) {
+		let num_dirty = self.tables.iter().filter(|(_, file_data)| file_data.dirty).count();
+		let title = match num_dirty {
+			0 => format!("CurvEdit {}", CURVEDIT_VERSION),
+			_ => format!("* CurvEdit {} - {} unsaved table(s)", CURVEDIT_VERSION, num_dirty)
+		};
+		if title != self.window_title {
+			ctx.send_viewport_cmd(ViewportCommand::Title(title.clone()));
+			self.window_title = title;
+		}
+	}
+	
+	pub(crate) fn try_open_file(&mut self, path: PathBuf) {
+		self.open_table(path.clone(), false);
+		if self.tables.iter().any(|(_, file_data)| file_data.file == path) {
+			self.remember_recent(&path, false);
+		}
+	}
+
+	//Opens a table, or every curve table below a directory, from the command line or dropped onto the window
+	pub(crate) fn open_path(&mut self, path: PathBuf) {
+		if path.is_dir() {
+			self.remember_recent(&path, true);
+			self.import_directory(&path, false);
+		}
+		else if path.exists() {
+			self.try_open_file(path);
+		}
+		else {
+			self.notes.push((Note {
+				text: format!("Cannot open {}, it does not exist!", path.to_string_lossy()),
+				severity: NoteSeverity::Error,
+				timeout: 5f32
+			}, None));
+		}
+	}
+
+	//Adds an already parsed table, in load order
+	pub(crate) fn add_table(&mut self, table: CurveTable, file_data: TableData) -> Result<(), String> {
+		if self.tables.iter().any(|(_, other)| other.file == file_data.file) {
+			return Err(format!("Cannot add table {}, it is already open", file_data.file.file_name().unwrap_or("".as_ref()).to_string_lossy()));
+		}
+		self.tables.push((table, file_data));
+		self.sort_tables();
+		Ok(())
+	}
+
+	pub(crate) fn report_overrides(&mut self, path: &Path) {
+		let Some(table_num) = self.tables.iter().position(|(_, file_data)| file_data.file == path) else { return; };
+		let overriding = self.tables[table_num].0.curves.iter().filter(|curve| {
+			self.tables.iter().take(table_num).any(|(table, _)| table.curves.iter().any(|other| other.name == curve.name))
+		}).map(|curve| curve.name.as_str()).collect::<Vec<&str>>();
+		let overridden = self.tables[table_num].0.curves.iter().filter(|curve| {
+			self.tables.iter().skip(table_num + 1).any(|(table, _)| table.curves.iter().any(|other| other.name == curve.name))
+		}).map(|curve| curve.name.as_str()).collect::<Vec<&str>>();
+		if !overriding.is_empty() || !overridden.is_empty() {
+			let mut text = path.file_name().unwrap_or("".as_ref()).to_string_lossy().to_string();
+			if !overriding.is_empty() {
+				text += &format!(" overrides {}", overriding.join(", "));
+			}
+			if !overridden.is_empty() {
+				text += &format!("{} is overridden in {}", if overriding.is_empty() { "" } else { ";" }, overridden.join(", "));
+			}
+			self.notes.push((Note {
+				text: format!("{}.", text),
+				severity: NoteSeverity::Info,
+				timeout: 5f32
+			}, None));
+		}
+	}
+
+	pub(crate) fn open_table(&mut self, path: PathBuf, read_only: bool) {
+		match self.load_table(path.clone(), read_only) {
+			Ok(_) => { self.report_overrides(&path); }
+			Err(error) => {
+				self.notes.push((Note {
+					text: format!("{}!", error),
+					severity: NoteSeverity::Error,
+					timeout: 5f32
+				}, None));
+			}
+		}
+	}
+
+	fn load_table(&mut self, path: PathBuf, read_only: bool) -> Result<(), String> {
+		let filename = path.file_name().unwrap_or("".as_ref()).to_string_lossy().to_string();
+		if !is_curve_table(&path) {
+			return Err(format!("{} is not a curves table", filename));
+		}
+		let table = FSOTableFileParser::new(&path).and_then(CurveTable::parse)
+			.map_err(|error| format!("Failed to parse {} at line {}: {}", filename, error.line, error.reason))?;
+		self.add_table(table, TableData { modified: modified_time(&path), file: path, dirty: false, read_only, ..Default::default() })
+	}
+
+	//Finds all curve tables of a mod root or tables folder, reporting everything that went wrong in a single note
+	pub(crate) fn import_directory(&mut self, path: &Path, read_only: bool) {
+		let mut files: Vec<PathBuf> = Vec::new();
+		let mut failures: Vec<String> = Vec::new();
+		find_curve_tables(path, &mut files, &mut failures);
+		files.sort_by_cached_key(|file| load_order_key(file));
+
+		let mut num_imported = 0;
+		let mut num_open = 0;
+		for file in files {
+			if self.tables.iter().any(|(_, file_data)| file_data.file == file) {
+				num_open += 1;
+			}
+			else {
+				match self.load_table(file, read_only) {
+					Ok(_) => { num_imported += 1; }
+					Err(error) => { failures.push(error); }
 				}
 			}
 		}
+
+		let mut text = format!("Imported {} table(s) from {}", num_imported, path.to_string_lossy());
+		if num_open > 0 {
+			text += &format!(", {} already open", num_open);
+		}
+		let severity = if !failures.is_empty() {
+			text += &format!(", {} failed: {}", failures.len(), failures.join("; "));
+			NoteSeverity::Error
+		}
+		else if num_imported + num_open == 0 {
+			text = format!("No curve tables found in {}", path.to_string_lossy());
+			NoteSeverity::Warning
+		}
 		else {
-			self.notes.push((Note {
-				text: format!("{} is not a curves table!", path.file_name().unwrap_or("".as_ref()).to_string_lossy()),
+			NoteSeverity::Info
+		};
+		self.notes.push((Note {
+			text: format!("{}{}", text, if severity == NoteSeverity::Info { "." } else { "!" }),
+			severity,
+			timeout: 5f32
+		}, None));
+	}
+}
+
+//FSO only loads tables from data/tables of a mod, so a mod root or data folder is narrowed down to that first
+pub(crate) fn find_curve_tables(dir: &Path, files: &mut Vec<PathBuf>, failures: &mut Vec<String>) {
+	let data_dir = child_dir(dir, "data").unwrap_or(dir.to_path_buf());
+	let tables_dir = child_dir(&data_dir, "tables").unwrap_or(data_dir);
+	let entries = match fs::read_dir(&tables_dir) {
+		Ok(entries) => entries,
+		Err(error) => {
+			failures.push(format!("Cannot read directory {}: {}", tables_dir.to_string_lossy(), error));
+			return;
+		}
+	};
+	for entry in entries.filter_map(Result::ok) {
+		let path = entry.path();
+		if !entry.file_type().is_ok_and(|file_type| file_type.is_dir()) && is_curve_table(&path) {
+			files.push(path);
+		}
+	}
+}
+
+//Folder names are matched ignoring case, like FSO does on Windows
+fn child_dir(dir: &Path, name: &str) -> Option<PathBuf> {
+	fs::read_dir(dir).ok()?.filter_map(Result::ok)
+		.find(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()) && entry.file_name().to_string_lossy().eq_ignore_ascii_case(name))
+		.map(|entry| entry.path())
+}
+
+//The table parser only reads from files, so tables that aren't loose files take a detour through a temporary one.
+//Its name includes the process ID, so several running instances don't overwrite each other's files.
+pub(crate) fn parse_table_text(content: &[u8], temp_name: &str) -> Result<CurveTable, String> {
+	let path = std::env::temp_dir().join(format!("curvedit-{}-{}", std::process::id(), temp_name));
+	fs::write(&path, content).map_err(|error| format!("cannot write temporary file: {}", error))?;
+	let table_parse = FSOTableFileParser::new(&path).and_then(CurveTable::parse);
+	let _ = fs::remove_file(&path);
+	table_parse.map_err(|error| format!("line {}: {}", error.line, error.reason))
+}
+
+//Returns whether the table was written
+pub(crate) fn save_table(table: &CurveTable, file_data: &mut TableData, notes: &mut Vec<(Note, Option<Instant>)>) -> bool {
+	if file_data.read_only {
+		return false;
+	}
+	match fs::write(&file_data.file, table.spew()) {
+		Ok(_) => {
+			file_data.dirty = false;
+			file_data.modified = modified_time(&file_data.file);
+			file_data.on_disk = None;
+			true
+		}
+		Err(error) => {
+			notes.push((Note {
+				text: format!("Cannot save table {}: {}!", file_data.file.file_name().unwrap_or("".as_ref()).to_string_lossy(), error),
 				severity: NoteSeverity::Error,
 				timeout: 5f32
 			}, None));
+			false
 		}
 	}
-}
\ No newline at end of file
+}
+
+//FSO only loads curves from curves.tbl and *-crv.tbm, so anything else gets the modular table suffix
+fn with_table_name(mut path: PathBuf) -> PathBuf {
+	let filename = path.file_name().unwrap_or("curves.tbl".as_ref()).to_string_lossy().to_ascii_lowercase();
+	if filename != "curves.tbl" && !filename.ends_with("-crv.tbm") {
+		path.set_file_name(path.file_name().map(|filename| {
+			let mut fname = filename.to_os_string();
+			fname.push("-crv.tbm");
+			fname
+		}).unwrap());
+	}
+	path
+}
+
+//curves.tbl is loaded first, then all modular tables in alphabetical order
+pub(crate) fn load_order_key(path: &Path) -> (bool, String, PathBuf) {
+	let filename = path.file_name().unwrap_or("".as_ref()).to_string_lossy().to_ascii_lowercase();
+	(filename != "curves.tbl", filename, path.to_path_buf())
+}
+
+pub(crate) fn is_curve_table(path: &Path) -> bool {
+	path.file_name().is_some_and(|filename| {
+		let filename = filename.to_string_lossy().to_ascii_lowercase();
+		filename == "curves.tbl" || filename.ends_with("-crv.tbm")
+	})
+}
@@ -1,10 +1,18 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use eframe::emath::{Align, Vec2};
 use egui::{Context, Id, Key, Layout, Ui, Widget};
 use egui::CursorIcon::{Grabbing, PointingHand};
+use egui_plot::{Legend, PlotTransform};
+use fso_tables_impl::curves::Curve;
 use crate::{CurvEdit, CurvEditInput};
-use crate::plot_panel::plot_curve;
+use crate::plot_panel::{get_available_curves, keyframe_bounds, KEYFRAME_MIN_X_DISTANCE, navigate_plot, plot_curves, plot_evaluate_at, plot_hover_readout, plot_outside_keyframes, range_bounds};
 
-pub(crate) const CURVE_RENDER_ACCURACY: usize = 1500;
+//Pixels between the grid lines that count as visible when snapping follows the plot
+const PLOT_GRID_MIN_SPACING: f64 = 40.0;
+pub(crate) const EXTRAPOLATION_MAX_MARGIN: f32 = 10.0;
+//Input range the extrapolation margin is relative to for curves whose keyframes cover (almost) none
+const EXTRAPOLATION_MIN_WIDTH: f32 = 1.0;
 
 #[derive(Default, PartialEq)]
 pub(crate) enum SnapMode {
@@ -15,9 +23,127 @@ pub(crate) enum SnapMode {
 	SnapCurve
 }
 
+//What keyframes snap to, on top of SnapMode, which only decides the axes keyframes move along
+pub(crate) struct SnapTargets {
+	//Round values, multiples of the steps
+	pub(crate) grid: bool,
+	pub(crate) step_x: f32,
+	pub(crate) step_y: f32,
+	//Use the spacing of the grid lines the plot currently shows instead of fixed steps
+	pub(crate) follow_plot: bool,
+	//Keyframes of all shown curves that are close by
+	pub(crate) keyframes: bool
+}
+
+impl Default for SnapTargets {
+	fn default() -> Self {
+		SnapTargets { grid: false, step_x: 0.05, step_y: 0.05, follow_plot: false, keyframes: false }
+	}
+}
+
+//Everything plot_curves needs to know to snap dragged and added keyframes
+pub(crate) struct Snapping<'a> {
+	pub(crate) mode: &'a SnapMode,
+	pub(crate) targets: &'a SnapTargets,
+	pub(crate) shown_curves: &'a [(usize, usize)]
+}
+
+impl SnapTargets {
+	pub(crate) fn grid_steps(&self, transform: &PlotTransform) -> Option<Vec2> {
+		if !self.grid {
+			return None;
+		}
+		if self.follow_plot {
+			//egui_plot places grid lines at powers of ten, fading out the ones that are too close together
+			let step = |value_per_pixel: f64| 10f64.powf((value_per_pixel.abs() * PLOT_GRID_MIN_SPACING).log10().ceil()) as f32;
+			let dvalue_dpos = transform.dvalue_dpos();
+			Some(Vec2::new(step(dvalue_dpos[0]), step(dvalue_dpos[1])))
+		}
+		else {
+			Some(Vec2::new(self.step_x, self.step_y))
+		}
+	}
+
+	//Plot-following increments depend on how far a plot is zoomed in, so outside of plots only fixed ones apply
+	pub(crate) fn fixed_grid_steps(&self) -> Option<Vec2> {
+		(self.grid && !self.follow_plot).then(|| Vec2::new(self.step_x, self.step_y))
+	}
+}
+
+//Plotting curves beyond their first and last keyframe, as the game evaluates them there too
+pub(crate) struct Extrapolation {
+	pub(crate) enabled: bool,
+	//Extra input range on each side, relative to the range of the keyframes
+	pub(crate) margin: f32,
+	//Curves that are always shown for the same input range instead, by file and name
+	pub(crate) fixed_ranges: HashMap<(PathBuf, String), (f32, f32)>
+}
+
+impl Default for Extrapolation {
+	fn default() -> Self {
+		Extrapolation { enabled: false, margin: 0.25, fixed_ranges: HashMap::new() }
+	}
+}
+
+impl Extrapolation {
+	//The input range to plot, if it reaches beyond the keyframes
+	pub(crate) fn domain(&self, file: &Path, curve: &Curve) -> Option<(f32, f32)> {
+		if !self.enabled || curve.keyframes.is_empty() {
+			return None;
+		}
+		let (first, last) = (curve.keyframes[0].pos.0, curve.keyframes[curve.keyframes.len() - 1].pos.0);
+		let (start, end) = match self.fixed_ranges.get(&(file.to_path_buf(), curve.name.clone())) {
+			Some(range) => *range,
+			None => {
+				let margin = (last - first).max(EXTRAPOLATION_MIN_WIDTH) * self.margin;
+				(first - margin, last + margin)
+			}
+		};
+		(start < first || end > last).then_some((start, end))
+	}
+}
+
+#[derive(Default, PartialEq)]
+pub(crate) enum DisplayMode {
+	#[default]
+	Stacked,
+	Overlay,
+	Grid
+}
+
 impl CurvEdit {
 	pub(crate) fn mode_panel(&mut self, ui: &mut Ui) {
-		//TODO v1.2 Display Modes
+		ui.label("Display: ");
+		if egui::Button::new("Stacked").selected(self.display_mode == DisplayMode::Stacked).ui(ui).on_hover_text("Show each curve in its own plot, one below the other.").clicked() {
+			self.display_mode = DisplayMode::Stacked;
+		}
+		if egui::Button::new("Overlay").selected(self.display_mode == DisplayMode::Overlay).ui(ui).on_hover_text("Show all curves in one shared plot.").clicked() {
+			self.display_mode = DisplayMode::Overlay;
+		}
+		if egui::Button::new("Grid").selected(self.display_mode == DisplayMode::Grid).ui(ui).on_hover_text("Show each curve in its own plot, arranged in a grid.").clicked() {
+			self.display_mode = DisplayMode::Grid;
+		}
+		
+		ui.separator();
+		ui.label("Nudge: ");
+		egui::DragValue::new(&mut self.nudge_steps.coarse).speed(0.01).clamp_range(KEYFRAME_MIN_X_DISTANCE..=f32::MAX).ui(ui).on_hover_text("How far the arrow keys move the selected keyframes.");
+		egui::DragValue::new(&mut self.nudge_steps.fine).speed(0.001).clamp_range(KEYFRAME_MIN_X_DISTANCE..=f32::MAX).ui(ui).on_hover_text("How far the arrow keys move the selected keyframes while Shift is held.");
+		
+		ui.separator();
+		ui.toggle_value(&mut self.extrapolation.enabled, "Outside keyframes").on_hover_text("Also plot the curves before their first and after their last keyframe.");
+		ui.add_enabled_ui(self.extrapolation.enabled, |ui| {
+			egui::DragValue::new(&mut self.extrapolation.margin).speed(0.01).clamp_range(0f32..=EXTRAPOLATION_MAX_MARGIN).ui(ui).on_hover_text("How far to plot beyond the keyframes, relative to the range they cover.");
+		});
+		
+		ui.separator();
+		ui.label("Evaluate at: ");
+		let id_evaluate_at = Id::new("EvaluateAt");
+		let mut evaluate_at = ui.memory(|mem| mem.data.get_temp::<String>(id_evaluate_at)).unwrap_or_else(|| self.evaluate_at.map(|x| x.to_string()).unwrap_or_default());
+		if egui::TextEdit::singleline(&mut evaluate_at).desired_width(60f32).hint_text("X").ui(ui).on_hover_text("Show the output of every shown curve at this input.").changed() {
+			self.evaluate_at = evaluate_at.trim().parse().ok();
+		}
+		ui.memory_mut(|mem| mem.data.insert_temp::<String>(id_evaluate_at, evaluate_at));
+		
 		ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
 			let response_curve = ui.scope(|ui| {
 				ui.set_style(self.noto_symbols_buttons.clone());
@@ -40,33 +166,72 @@ impl CurvEdit {
 				self.snap_mode = SnapMode::NoSnap;
 			}
 			ui.label("Snap to axis: ");
+			ui.separator();
+			
+			ui.toggle_value(&mut self.snap_targets.keyframes, "Keyframes").on_hover_text("Snap dragged and added keyframes to nearby keyframes of all shown curves.");
+			ui.add_enabled_ui(self.snap_targets.grid, |ui| {
+				ui.toggle_value(&mut self.snap_targets.follow_plot, "Follow plot").on_hover_text("Snap to the grid lines the plot shows, finer when zoomed in.");
+				ui.add_enabled_ui(!self.snap_targets.follow_plot, |ui| {
+					egui::DragValue::new(&mut self.snap_targets.step_y).speed(0.01).clamp_range(KEYFRAME_MIN_X_DISTANCE..=f32::MAX).ui(ui).on_hover_text("Y increment.");
+					egui::DragValue::new(&mut self.snap_targets.step_x).speed(0.01).clamp_range(KEYFRAME_MIN_X_DISTANCE..=f32::MAX).ui(ui).on_hover_text("X increment.");
+				});
+			});
+			ui.toggle_value(&mut self.snap_targets.grid, "Grid").on_hover_text("Snap dragged and added keyframes to multiples of the increments.");
+			ui.label("Snap to: ");
 		});
 	}
 	
 	pub(crate) fn curve_panel(&mut self, ui: &mut Ui, ctx: &Context) {
+		let nothing_focused = ctx.memory(|mem| mem.focused().is_none());
 		let input = ui.input(|i| { CurvEditInput { 
 			pointer_down: i.pointer.primary_down(),
+			pointer_pressed: i.pointer.primary_pressed(),
 			right_clicked: i.pointer.secondary_pressed(),
 			ctrl_held: i.modifiers.ctrl,
-			escape_pressed: i.key_pressed(Key::Escape)
+			shift_held: i.modifiers.shift,
+			escape_pressed: i.key_pressed(Key::Escape),
+			insert_pressed: i.key_pressed(Key::Insert) && nothing_focused && !self.close_dialog
 		} });
-		let cursor_group = Id::new("CursorGroup");
-		let height = ui.available_height() / (self.curves_to_show.len() as f32) - 3f32;
 		let mut is_dragging = false;
+		let mut is_hovered = false;
+		self.check_subcurve_cycles();
+		let curves = self.curves_to_show.clone();
 
-		//TODO v1.2 different plot modes
-		for curve in &self.curves_to_show {
-			ui.allocate_ui_with_layout(Vec2::new(ui.available_width(), height), Layout::top_down(Align::Center), |ui| {
-				let name = self.tables[curve.0].0.curves[curve.1].name.as_str();
-				ui.label(name);
-				egui_plot::Plot::new(name)
-					.allow_zoom(false)
-					.allow_drag(false)
-					.allow_scroll(false)
-					.allow_boxed_zoom(false)
-					.link_cursor(cursor_group, true, false)
-					.show(ui, |plot_ui| plot_curve(plot_ui, ctx, &input, &mut self.tables, curve, &self.snap_mode, &mut is_dragging, &mut self.selected_keyframe))
-			});
+		match self.display_mode {
+			DisplayMode::Stacked => {
+				let height = ui.available_height() / (curves.len() as f32) - 3f32;
+				for curve in curves.iter() {
+					ui.allocate_ui_with_layout(Vec2::new(ui.available_width(), height), Layout::top_down(Align::Center), |ui| {
+						self.curve_plot(ui, ctx, &input, std::slice::from_ref(curve), &mut is_dragging, &mut is_hovered);
+					});
+				}
+			}
+			DisplayMode::Overlay => {
+				if !curves.is_empty() {
+					self.curve_plot(ui, ctx, &input, &curves, &mut is_dragging, &mut is_hovered);
+				}
+			}
+			DisplayMode::Grid => {
+				if !curves.is_empty() {
+					let columns = (curves.len() as f32).sqrt().ceil() as usize;
+					let rows = curves.len().div_ceil(columns);
+					let size = Vec2::new(ui.available_width() / (columns as f32) - 3f32, ui.available_height() / (rows as f32) - 3f32);
+					for row in curves.chunks(columns) {
+						ui.horizontal(|ui| {
+							for curve in row {
+								ui.allocate_ui_with_layout(size, Layout::top_down(Align::Center), |ui| {
+									self.curve_plot(ui, ctx, &input, std::slice::from_ref(curve), &mut is_dragging, &mut is_hovered);
+								});
+							}
+						});
+					}
+				}
+			}
+		}
+
+		//Insert adds a keyframe at the pointer when it's over a plot, else right after the selected keyframe
+		if input.insert_pressed && !is_hovered {
+			self.insert_keyframe_after_selected();
 		}
 
 		if is_dragging {
@@ -76,4 +241,123 @@ impl CurvEdit {
 			ctx.output_mut(|o| o.cursor_icon = PointingHand);
 		}
 	}
-}
\ No newline at end of file
+	
+	fn curve_plot(&mut self, ui: &mut Ui, ctx: &Context, input: &CurvEditInput, curves: &[(usize, usize)], is_dragging: &mut bool, is_hovered: &mut bool) {
+		let cursor_group = Id::new("CursorGroup");
+		let overlay = curves.len() > 1;
+		let name_of = |(table, curve): &(usize, usize)| self.tables[*table].0.curves[*curve].name.clone();
+		let (cyclic, curves): (Vec<_>, Vec<_>) = curves.iter().partition(|curve| self.is_cyclic(**curve));
+		
+		let plot_name = if overlay { "Overlay".to_string() } else { curves.first().or(cyclic.first()).map(name_of).unwrap_or_default() };
+		//Overrides share their name with the curve they replace, so single plots are told apart by their table too
+		let plot_id = if overlay { Id::new("Overlay") } else { curves.first().or(cyclic.first()).map_or(Id::new(&plot_name), |(table, _)| Id::new(&self.tables[*table].1.file).with(&plot_name)) };
+		
+		let (fit_keyframes, fit_range) = ui.horizontal(|ui| {
+			if !overlay {
+				ui.label(&plot_name);
+			}
+			else if !cyclic.is_empty() {
+				ui.label(format!("Cyclic subcurve references, cannot plot: {}", cyclic.iter().map(name_of).collect::<Vec<String>>().join(", ")));
+			}
+			if let Some(x) = self.evaluate_at {
+				let available_curves = get_available_curves(&self.tables);
+				let outputs = curves.iter().map(|(table, curve)| {
+					let curve = &self.tables[*table].0.curves[*curve];
+					format!("{}({}) = {}", curve.name, x, curve.calculate(x, &available_curves))
+				}).collect::<Vec<String>>();
+				if !outputs.is_empty() {
+					ui.separator();
+					ui.label(outputs.join(", "));
+				}
+			}
+			ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+				if !overlay && self.extrapolation.enabled {
+					if let Some((file, curve)) = curves.first().map(|(table, curve)| (&self.tables[*table].1.file, &self.tables[*table].0.curves[*curve])) {
+						input_range_menu(ui, file, curve, &mut self.extrapolation.fixed_ranges);
+					}
+				}
+				let fit_range = ui.add_enabled(!curves.is_empty(), egui::Button::new("Fit to range").small()).on_hover_text("Show the whole range of the curve.").clicked();
+				let fit_keyframes = ui.add_enabled(!curves.is_empty(), egui::Button::new("Fit to keyframes").small()).on_hover_text("Show all keyframes of the curve.").clicked();
+				(fit_keyframes, fit_range)
+			}).inner
+		}).inner;
+		
+		if curves.is_empty() {
+			ui.centered_and_justified(|ui| ui.label("This curve has cyclic subcurve references and cannot be plotted."));
+			return;
+		}
+		
+		let plotted_curves = curves.iter().map(|(table, curve)| &self.tables[*table].0.curves[*curve]).collect::<Vec<&Curve>>();
+		let fit = if fit_keyframes { Some(keyframe_bounds(&plotted_curves)) } else if fit_range { Some(range_bounds(&plotted_curves)) } else { None };
+		
+		//Views are kept while the plot is visible, and start fresh when it is shown again
+		let id_last_shown = plot_id.with("LastShown");
+		let last_shown = ctx.memory(|mem| mem.data.get_temp::<u64>(id_last_shown));
+		ctx.memory_mut(|mem| mem.data.insert_temp::<u64>(id_last_shown, ctx.frame_nr()));
+		
+		let mut plot = egui_plot::Plot::new(plot_id)
+			.allow_zoom(false)
+			.allow_drag(false)
+			.allow_scroll(false)
+			.allow_boxed_zoom(false)
+			.allow_double_click_reset(false)
+			.link_cursor(cursor_group, true, false);
+		if overlay {
+			plot = plot.legend(Legend::default());
+		}
+		let was_hidden = match last_shown {
+			Some(frame) => frame + 1 < ctx.frame_nr(),
+			None => true
+		};
+		if was_hidden {
+			plot = plot.reset();
+		}
+		let snapping = Snapping { mode: &self.snap_mode, targets: &self.snap_targets, shown_curves: &self.curves_to_show };
+		//The pointer input is shared between the linked plots, the plots drawn before the hovered one use the last frame's
+		let id_pointer_x = cursor_group.with("PointerX");
+		let pointer_x = ctx.memory(|mem| mem.data.get_temp::<(u64, f32)>(id_pointer_x)).filter(|(frame, _)| frame + 1 >= ctx.frame_nr()).map(|(_, x)| x);
+		let response = plot.show(ui, |plot_ui| {
+			navigate_plot(plot_ui, ctx, fit);
+			plot_outside_keyframes(plot_ui, &self.tables, &curves, &self.extrapolation, &mut self.samples);
+			plot_curves(plot_ui, ctx, input, &mut self.tables, &curves, &snapping, &mut self.samples, is_dragging, &mut self.selected_keyframes);
+			if let Some(x) = self.evaluate_at {
+				plot_evaluate_at(plot_ui, &self.tables, &curves, x);
+			}
+			let hovered_x = plot_ui.response().hovered().then(|| plot_ui.pointer_coordinate()).flatten().map(|pointer| pointer.x as f32);
+			if let Some(x) = hovered_x.or(pointer_x) {
+				plot_hover_readout(plot_ui, &self.tables, &curves, x);
+			}
+			hovered_x
+		});
+		if let Some(x) = response.inner {
+			ctx.memory_mut(|mem| mem.data.insert_temp::<(u64, f32)>(id_pointer_x, (ctx.frame_nr(), x)));
+		}
+		let response = response.response;
+		*is_hovered |= response.hovered();
+	}
+}
+
+//Lets a curve be plotted for a fixed input range, such as the distances a weapon can actually reach
+fn input_range_menu(ui: &mut Ui, file: &Path, curve: &Curve, fixed_ranges: &mut HashMap<(PathBuf, String), (f32, f32)>) {
+	let key = (file.to_path_buf(), curve.name.clone());
+	ui.menu_button("Input range", |ui| {
+		let mut fixed = fixed_ranges.contains_key(&key);
+		if ui.checkbox(&mut fixed, "Fixed input range").on_hover_text("Plot this curve for the same input range, wherever its keyframes are.").changed() {
+			if fixed {
+				let (first, last) = curve.keyframes.first().zip(curve.keyframes.last()).map_or((0f32, 1f32), |(first, last)| (first.pos.0, last.pos.0));
+				fixed_ranges.insert(key.clone(), (first, last));
+			}
+			else {
+				fixed_ranges.remove(&key);
+			}
+		}
+		if let Some((start, end)) = fixed_ranges.get_mut(&key) {
+			ui.horizontal(|ui| {
+				ui.label("From: ");
+				egui::DragValue::new(start).speed(0.1).clamp_range(f32::MIN..=*end).ui(ui);
+				ui.label("To: ");
+				egui::DragValue::new(end).speed(0.1).clamp_range(*start..=f32::MAX).ui(ui);
+			});
+		}
+	}).response.on_hover_text("Choose the inputs this curve is plotted for.");
+}
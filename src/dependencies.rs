@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet};
+use fso_tables_impl::curves::{Curve, CurveSegment};
+use crate::CurvEdit;
+use crate::note_bar::{Note, NoteSeverity};
+use crate::plot_panel::get_available_curves;
+
+pub(crate) struct DependencyGraph {
+	subcurves: HashMap<String, Vec<String>>
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+	Visiting,
+	Terminates,
+	Cyclic
+}
+
+impl DependencyGraph {
+	pub(crate) fn new(curves: &[&Curve]) -> Self {
+		let mut subcurves: HashMap<String, Vec<String>> = HashMap::new();
+		for curve in curves.iter() {
+			//Like in FSO, a later curve of the same name overrides the earlier one
+			subcurves.insert(curve.name.clone(), subcurves_of(curve).map(str::to_string).collect());
+		}
+		DependencyGraph { subcurves }
+	}
+
+	//Applies a rename the same way renaming a curve rewrites the subcurve references to it
+	pub(crate) fn rename(&mut self, old_name: &str, new_name: &str) {
+		if let Some(targets) = self.subcurves.remove(old_name) {
+			self.subcurves.insert(new_name.to_string(), targets);
+		}
+		for targets in self.subcurves.values_mut() {
+			for target in targets.iter_mut().filter(|target| *target == old_name) {
+				*target = new_name.to_string();
+			}
+		}
+	}
+
+	pub(crate) fn depends_on(&self, curve: &str, other: &str) -> bool {
+		let mut visited: HashSet<&str> = HashSet::new();
+		let mut to_visit: Vec<&str> = vec![curve];
+		while let Some(next) = to_visit.pop() {
+			if next == other {
+				return true;
+			}
+			if visited.insert(next) {
+				if let Some(targets) = self.subcurves.get(next) {
+					to_visit.extend(targets.iter().map(String::as_str));
+				}
+			}
+		}
+		false
+	}
+
+	pub(crate) fn is_on_cycle(&self, curve: &str) -> bool {
+		self.subcurves.get(curve).is_some_and(|targets| targets.iter().any(|target| self.depends_on(target, curve)))
+	}
+
+	pub(crate) fn would_create_cycle(&self, curve: &str, subcurve: &str) -> bool {
+		self.depends_on(subcurve, curve)
+	}
+
+	//All curves that can't be evaluated, because they are part of a cycle or use a curve that is
+	pub(crate) fn cyclic_curves(&self) -> HashSet<String> {
+		let mut states: HashMap<&str, VisitState> = HashMap::new();
+		for curve in self.subcurves.keys() {
+			self.visit(curve, &mut states);
+		}
+		states.into_iter().filter(|(_, state)| *state == VisitState::Cyclic).map(|(curve, _)| curve.to_string()).collect()
+	}
+
+	fn visit<'a>(&'a self, curve: &'a str, states: &mut HashMap<&'a str, VisitState>) -> VisitState {
+		if let Some(state) = states.get(curve) {
+			return if *state == VisitState::Visiting { VisitState::Cyclic } else { *state };
+		}
+
+		states.insert(curve, VisitState::Visiting);
+		let mut state = VisitState::Terminates;
+		for target in self.subcurves.get(curve).into_iter().flatten() {
+			if self.visit(target, states) == VisitState::Cyclic {
+				state = VisitState::Cyclic;
+			}
+		}
+		states.insert(curve, state);
+		state
+	}
+}
+
+pub(crate) fn subcurves_of(curve: &Curve) -> impl Iterator<Item = &str> {
+	curve.keyframes.iter().filter_map(|keyframe| match &keyframe.segment {
+		CurveSegment::Subcurve { curve } => Some(curve.as_str()),
+		_ => None
+	})
+}
+
+impl CurvEdit {
+	//Subcurves are looked up by name, so this also holds for shadowed definitions using a cyclic curve
+	pub(crate) fn is_cyclic(&self, (table, curve): (usize, usize)) -> bool {
+		subcurves_of(&self.tables[table].0.curves[curve]).any(|subcurve| self.cyclic_curves.contains(subcurve))
+	}
+
+	//Runs at the start of the frame and again right before plotting, as the panels in between can add curves
+	pub(crate) fn check_subcurve_cycles(&mut self) {
+		let cyclic_curves = DependencyGraph::new(&get_available_curves(&self.tables)).cyclic_curves();
+
+		let mut newly_cyclic = cyclic_curves.iter().filter(|curve| !self.cyclic_curves.contains(*curve)).cloned().collect::<Vec<String>>();
+		if !newly_cyclic.is_empty() {
+			newly_cyclic.sort();
+			self.notes.push((Note {
+				text: format!("Cyclic subcurve references, cannot plot {}!", newly_cyclic.join(", ")),
+				severity: NoteSeverity::Warning,
+				timeout: 5f32
+			}, None));
+		}
+
+		self.cyclic_curves = cyclic_curves;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use fso_tables_impl::curves::{Curve, CurveKeyframe, CurveSegment};
+	use super::DependencyGraph;
+
+	fn curve(name: &str, subcurves: &[&str]) -> Curve {
+		let mut keyframes = vec![CurveKeyframe::new((0f32, 0f32), CurveSegment::Linear)];
+		keyframes.extend(subcurves.iter().enumerate().map(|(i, subcurve)| CurveKeyframe::new(((i + 1) as f32, 0f32), CurveSegment::Subcurve { curve: subcurve.to_string() })));
+		Curve::new(name.to_string(), keyframes)
+	}
+
+	fn graph(curves: &[Curve]) -> DependencyGraph {
+		DependencyGraph::new(&curves.iter().collect::<Vec<&Curve>>())
+	}
+
+	fn sorted(mut names: Vec<String>) -> Vec<String> {
+		names.sort();
+		names
+	}
+
+	#[test]
+	fn no_cycles() {
+		let graph = graph(&[curve("A", &["B"]), curve("B", &["C"]), curve("C", &[])]);
+		assert!(graph.cyclic_curves().is_empty());
+	}
+
+	#[test]
+	fn self_reference_is_cyclic() {
+		let graph = graph(&[curve("A", &["A"]), curve("B", &[])]);
+		assert_eq!(sorted(graph.cyclic_curves().into_iter().collect()), vec!["A"]);
+	}
+
+	#[test]
+	fn users_of_a_cycle_are_cyclic() {
+		let graph = graph(&[curve("A", &["B"]), curve("B", &["C"]), curve("C", &["B"]), curve("D", &["A"]), curve("E", &["F"]), curve("F", &[])]);
+		assert_eq!(sorted(graph.cyclic_curves().into_iter().collect()), vec!["A", "B", "C", "D"]);
+	}
+
+	#[test]
+	fn later_definitions_override() {
+		let overridden_by_cyclic = graph(&[curve("A", &[]), curve("A", &["A"])]);
+		assert_eq!(sorted(overridden_by_cyclic.cyclic_curves().into_iter().collect()), vec!["A"]);
+		let cyclic_overridden = graph(&[curve("A", &["A"]), curve("A", &[])]);
+		assert!(cyclic_overridden.cyclic_curves().is_empty());
+	}
+
+	#[test]
+	fn would_create_cycle() {
+		let graph = graph(&[curve("A", &["B"]), curve("B", &["C"]), curve("C", &[])]);
+		assert!(graph.would_create_cycle("C", "A"));
+		assert!(graph.would_create_cycle("C", "C"));
+		assert!(graph.would_create_cycle("B", "A"));
+		assert!(!graph.would_create_cycle("A", "C"));
+		assert!(!graph.would_create_cycle("C", "D"));
+	}
+
+	#[test]
+	fn would_create_cycle_after_rename() {
+		let mut graph = graph(&[curve("A", &["B"]), curve("B", &[]), curve("C", &[])]);
+		graph.rename("B", "D");
+		assert!(graph.would_create_cycle("D", "A"));
+		assert!(!graph.would_create_cycle("C", "A"));
+	}
+}
@@ -0,0 +1,182 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+use egui::{Align2, Color32, Vec2};
+use fso_tables_impl::curves::{Curve, CurveKeyframe, CurveSegment, CurveTable};
+use fso_tables_impl::FSOTableFileParser;
+use crate::CurvEdit;
+use crate::history::curve_equal;
+use crate::note_bar::{Note, NoteSeverity};
+
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub(crate) enum DiskAction {
+	Reload,
+	KeepOurs,
+	ShowDiff
+}
+
+impl CurvEdit {
+	pub(crate) fn check_files_on_disk(&mut self, ctx: &egui::Context) {
+		ctx.request_repaint_after(DISK_CHECK_INTERVAL);
+		if self.last_disk_check.is_some_and(|last_check| last_check.elapsed() < DISK_CHECK_INTERVAL) {
+			return;
+		}
+		self.last_disk_check = Some(Instant::now());
+
+		for table_num in 0..self.tables.len() {
+			let file_data = &self.tables[table_num].1;
+			//Files that vanished are left alone, the next save will recreate them
+			let Some(modified) = modified_time(&file_data.file) else { continue; };
+			if file_data.modified == Some(modified) {
+				continue;
+			}
+			let filename = file_data.file.file_name().unwrap_or("".as_ref()).to_string_lossy().to_string();
+
+			let table_parse = FSOTableFileParser::new(&file_data.file).and_then(CurveTable::parse);
+			match table_parse {
+				Ok(on_disk) if !self.tables[table_num].1.dirty => {
+					self.reload_table(table_num, on_disk.curves);
+					self.tables[table_num].1.modified = Some(modified);
+					self.notes.push((Note {
+						text: format!("Reloaded {}, it was changed on disk.", filename),
+						severity: NoteSeverity::Info,
+						timeout: 3f32
+					}, None));
+				}
+				Ok(on_disk) => {
+					let file_data = &mut self.tables[table_num].1;
+					file_data.on_disk = Some(on_disk);
+					file_data.modified = Some(modified);
+					self.notes.push((Note {
+						text: format!("{} was changed on disk, but also has unsaved changes here! Choose what to keep in the table list.", filename),
+						severity: NoteSeverity::Warning,
+						timeout: 8f32
+					}, None));
+				}
+				Err(error) => {
+					//Most likely caught mid-write, the next change to the file will be picked up again
+					self.tables[table_num].1.modified = Some(modified);
+					self.notes.push((Note {
+						text: format!("{} was changed on disk, but failed to parse at line {}: {}!", filename, error.line, error.reason),
+						severity: NoteSeverity::Error,
+						timeout: 5f32
+					}, None));
+				}
+			}
+		}
+	}
+
+	pub(crate) fn resolve_disk_conflict(&mut self, table_num: usize, action: DiskAction) {
+		match action {
+			DiskAction::Reload => {
+				if let Some(on_disk) = self.tables[table_num].1.on_disk.take() {
+					self.reload_table(table_num, on_disk.curves);
+					self.tables[table_num].1.dirty = false;
+				}
+				self.disk_diff = None;
+			}
+			DiskAction::KeepOurs => {
+				//Our version stays dirty, so the next save overwrites the file
+				self.tables[table_num].1.on_disk = None;
+				self.disk_diff = None;
+			}
+			DiskAction::ShowDiff => {
+				self.disk_diff = Some(self.tables[table_num].1.file.clone());
+			}
+		}
+	}
+
+	fn reload_table(&mut self, table_num: usize, curves: Vec<Curve>) {
+		self.preserving_selection(|curvedit| {
+			curvedit.tables[table_num].0 = CurveTable::new(curves);
+		});
+	}
+
+	pub(crate) fn disk_diff_dialog(&mut self, ctx: &egui::Context) {
+		let Some(file) = &self.disk_diff else { return; };
+		let Some(table_num) = self.tables.iter().position(|(_, file_data)| file_data.file == *file && file_data.on_disk.is_some()) else {
+			self.disk_diff = None;
+			return;
+		};
+		let (ours, file_data) = &self.tables[table_num];
+		let Some(theirs) = &file_data.on_disk else { return; };
+
+		let mut action: Option<DiskAction> = None;
+		let mut open = true;
+		egui::Window::new(format!("Changes on disk: {}", file_data.file.file_name().unwrap_or("".as_ref()).to_string_lossy()))
+			.collapsible(false)
+			.open(&mut open)
+			.anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+			.show(ctx, |ui| {
+				egui::ScrollArea::vertical().max_height(400f32).show(ui, |ui| {
+					let mut any_differences = false;
+					for curve in ours.curves.iter() {
+						match theirs.curves.iter().find(|other| other.name == curve.name) {
+							None => {
+								ui.colored_label(Color32::from_rgb(64, 140, 255), format!("{}: Only here, removed on disk", curve.name));
+								any_differences = true;
+							}
+							Some(other) if !curve_equal(curve, other) => {
+								ui.collapsing(format!("{}: Changed", curve.name), |ui| {
+									keyframe_diff(ui, &curve.keyframes, &other.keyframes);
+								});
+								any_differences = true;
+							}
+							Some(_) => {}
+						}
+					}
+					for curve in theirs.curves.iter().filter(|curve| !ours.curves.iter().any(|other| other.name == curve.name)) {
+						ui.colored_label(Color32::from_rgb(255, 191, 64), format!("{}: Only on disk, added there", curve.name));
+						any_differences = true;
+					}
+					if !any_differences {
+						ui.label("The curves are identical, only the file changed.");
+					}
+				});
+				ui.separator();
+				ui.horizontal(|ui| {
+					if ui.button("Reload").on_hover_text("Discard the changes made here and use the version on disk.").clicked() {
+						action = Some(DiskAction::Reload);
+					}
+					if ui.button("Keep ours").on_hover_text("Keep the changes made here, saving will overwrite the file.").clicked() {
+						action = Some(DiskAction::KeepOurs);
+					}
+				});
+			});
+
+		if !open {
+			self.disk_diff = None;
+		}
+		if let Some(action) = action {
+			self.resolve_disk_conflict(table_num, action);
+		}
+	}
+}
+
+fn keyframe_diff(ui: &mut egui::Ui, ours: &[CurveKeyframe], theirs: &[CurveKeyframe]) {
+	for kf_num in 0..ours.len().max(theirs.len()) {
+		match (ours.get(kf_num), theirs.get(kf_num)) {
+			(Some(ours), Some(theirs)) if ours.pos == theirs.pos && ours.segment == theirs.segment => {}
+			(Some(ours), Some(theirs)) => { ui.label(format!("Keyframe {}: {} here, {} on disk", kf_num + 1, keyframe_text(ours), keyframe_text(theirs))); }
+			(Some(ours), None) => { ui.label(format!("Keyframe {}: {} here, missing on disk", kf_num + 1, keyframe_text(ours))); }
+			(None, Some(theirs)) => { ui.label(format!("Keyframe {}: missing here, {} on disk", kf_num + 1, keyframe_text(theirs))); }
+			(None, None) => {}
+		}
+	}
+}
+
+fn keyframe_text(keyframe: &CurveKeyframe) -> String {
+	let segment = match &keyframe.segment {
+		CurveSegment::Constant => { "Constant".to_string() }
+		CurveSegment::Linear => { "Linear".to_string() }
+		CurveSegment::Polynomial { degree, .. } => { format!("Polynomial {}", degree) }
+		CurveSegment::Circular { .. } => { "Circular".to_string() }
+		CurveSegment::Subcurve { curve } => { format!("Subcurve {}", curve) }
+	};
+	format!("({}, {}) {}", keyframe.pos.0, keyframe.pos.1, segment)
+}
+
+pub(crate) fn modified_time(path: &Path) -> Option<SystemTime> {
+	fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
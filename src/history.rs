@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::mem::replace;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use egui::{Key, KeyboardShortcut, Modifiers};
+use fso_tables_impl::curves::{Curve, CurveTable};
+use crate::{CurvEdit, TableData};
+
+const HISTORY_LENGTH: usize = 256;
+
+pub(crate) const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+pub(crate) const REDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+
+struct TableState {
+	curves: Vec<Curve>,
+	file: PathBuf,
+	revision: u64,
+	//Needed to bring back tables that were closed in the meantime as they were
+	modified: Option<SystemTime>,
+	read_only: bool
+}
+
+#[derive(Default)]
+pub(crate) struct History {
+	undo: Vec<Vec<TableState>>,
+	redo: Vec<Vec<TableState>>,
+	current: Vec<TableState>,
+	//The revision of each table file that matches what is on disk
+	saved_revisions: HashMap<PathBuf, u64>,
+	last_revision: u64
+}
+
+impl History {
+	pub(crate) fn can_undo(&self) -> bool {
+		!self.undo.is_empty()
+	}
+
+	pub(crate) fn can_redo(&self) -> bool {
+		!self.redo.is_empty()
+	}
+
+	//Saving under a new name shouldn't show up as a change of its own
+	pub(crate) fn file_renamed(&mut self, old_file: &Path, new_file: &Path) {
+		for state in self.undo.iter_mut().chain(self.redo.iter_mut()).flatten().chain(self.current.iter_mut()) {
+			if state.file == *old_file {
+				state.file = new_file.to_path_buf();
+			}
+		}
+		self.saved_revisions.remove(old_file);
+	}
+
+	fn record(&mut self, tables: &[(CurveTable, TableData)]) {
+		let changed = tables.len() != self.current.len() || tables.iter().zip(self.current.iter()).any(|((table, file_data), state)| {
+			file_data.file != state.file || !curves_equal(&table.curves, &state.curves)
+		});
+
+		if changed {
+			let new_state = tables.iter().map(|(table, file_data)| {
+				let unchanged = self.current.iter().find(|state| state.file == file_data.file && curves_equal(&table.curves, &state.curves));
+				let revision = match unchanged {
+					Some(state) => state.revision,
+					None => {
+						self.last_revision += 1;
+						self.last_revision
+					}
+				};
+				TableState { curves: table.curves.clone(), file: file_data.file.clone(), revision, modified: file_data.modified, read_only: file_data.read_only }
+			}).collect();
+
+			self.undo.push(replace(&mut self.current, new_state));
+			if self.undo.len() > HISTORY_LENGTH {
+				self.undo.remove(0);
+			}
+			self.redo.clear();
+		}
+
+		for (state, (_, file_data)) in self.current.iter().zip(tables.iter()) {
+			if !file_data.dirty {
+				self.saved_revisions.insert(file_data.file.clone(), state.revision);
+			}
+		}
+	}
+
+	fn restore(&self, tables: &mut Vec<(CurveTable, TableData)>) {
+		let mut old_tables = std::mem::take(tables);
+		for state in self.current.iter() {
+			//Keep whatever else we know about the file, if it's still open
+			let mut file_data = match old_tables.iter().position(|(_, file_data)| file_data.file == state.file) {
+				Some(idx) => old_tables.remove(idx).1,
+				None => TableData { file: state.file.clone(), modified: state.modified, read_only: state.read_only, ..Default::default() }
+			};
+			file_data.dirty = self.saved_revisions.get(&state.file) != Some(&state.revision);
+			tables.push((CurveTable::new(state.curves.clone()), file_data));
+		}
+	}
+}
+
+impl CurvEdit {
+	pub(crate) fn record_history(&mut self) {
+		self.history.record(&self.tables);
+	}
+
+	pub(crate) fn undo(&mut self) {
+		if let Some(state) = self.history.undo.pop() {
+			let state = replace(&mut self.history.current, state);
+			self.history.redo.push(state);
+			self.restore_history();
+		}
+	}
+
+	pub(crate) fn redo(&mut self) {
+		if let Some(state) = self.history.redo.pop() {
+			let state = replace(&mut self.history.current, state);
+			self.history.undo.push(state);
+			self.restore_history();
+		}
+	}
+
+	fn restore_history(&mut self) {
+		self.preserving_selection(|curvedit| curvedit.history.restore(&mut curvedit.tables));
+	}
+}
+
+fn curves_equal(a: &[Curve], b: &[Curve]) -> bool {
+	a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| curve_equal(a, b))
+}
+
+pub(crate) fn curve_equal(a: &Curve, b: &Curve) -> bool {
+	a.name == b.name && a.keyframes.len() == b.keyframes.len() && a.keyframes.iter().zip(b.keyframes.iter()).all(|(a, b)| {
+		a.pos == b.pos && a.segment == b.segment
+	})
+}
@@ -0,0 +1,204 @@
+use eframe::emath::Vec2;
+use egui::{Key, Modifiers};
+use fso_tables_impl::curves::{CurveKeyframe, CurveSegment};
+use crate::CurvEdit;
+use crate::curves_panel::SnapMode;
+use crate::note_bar::{Note, NoteSeverity};
+use crate::plot_panel::{calculate_new_keyframe_pos, get_available_curves, keyframe_offset_bounds, KEYFRAME_MIN_X_DISTANCE};
+use crate::selection::{keyframe_inserted, keyframe_removed, select_only, selected_in_curve};
+
+//How far the arrow keys move keyframes, fine steps are used while Shift is held
+#[derive(Clone, Copy)]
+pub(crate) struct NudgeSteps {
+	pub(crate) coarse: f32,
+	pub(crate) fine: f32
+}
+
+impl Default for NudgeSteps {
+	fn default() -> Self {
+		NudgeSteps { coarse: 0.1, fine: 0.01 }
+	}
+}
+
+impl CurvEdit {
+	//Keyframe editing while no text field has focus: Arrows nudge, Tab steps through keyframes, Delete removes and Insert adds.
+	//Returns whether Tab was used, as egui would otherwise hand the focus to the first widget.
+	pub(crate) fn keyboard_editing(&mut self, ctx: &egui::Context) -> bool {
+		let (arrows, fine) = ctx.input(|i| {
+			let arrow = |key: Key| if i.key_pressed(key) { 1f32 } else { 0f32 };
+			(Vec2::new(arrow(Key::ArrowRight) - arrow(Key::ArrowLeft), arrow(Key::ArrowUp) - arrow(Key::ArrowDown)), i.modifiers.shift)
+		});
+		if arrows != Vec2::ZERO {
+			let step = Vec2::splat(if fine { self.nudge_steps.fine } else { self.nudge_steps.coarse });
+			//Steps smaller than the grid would snap right back to where the keyframes were
+			let grid = self.snap_targets.fixed_grid_steps();
+			let step = grid.map_or(step, |grid| step.max(grid));
+			self.nudge_selected_keyframes(arrows * step, grid);
+		}
+
+		//Shift is ignored unless asked for, so the combinations with Shift go first
+		let previous_curve = ctx.input_mut(|i| i.consume_key(Modifiers::CTRL | Modifiers::SHIFT, Key::Tab));
+		let next_curve = ctx.input_mut(|i| i.consume_key(Modifiers::CTRL, Key::Tab));
+		let previous = ctx.input_mut(|i| i.consume_key(Modifiers::SHIFT, Key::Tab));
+		let next = ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Tab));
+		if next_curve || previous_curve {
+			self.step_curve(previous_curve);
+		}
+		else if next || previous {
+			self.step_keyframe(previous);
+		}
+
+		if ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Delete)) {
+			self.delete_selected_keyframes();
+		}
+		next || previous || next_curve || previous_curve
+	}
+
+	//Moves the selected keyframes like dragging them would, so the snap mode and grid apply and they never pass their neighbours
+	fn nudge_selected_keyframes(&mut self, offset: Vec2, grid: Option<Vec2>) {
+		let offset = match self.snap_mode {
+			SnapMode::NoSnap => offset,
+			SnapMode::SnapX | SnapMode::SnapCurve => Vec2::new(offset.x, 0f32),
+			SnapMode::SnapY => Vec2::new(0f32, offset.y)
+		};
+		if offset == Vec2::ZERO {
+			return;
+		}
+
+		//Snapping to a cyclic curve would evaluate it forever, so those keep their keyframes where they are
+		let mut curves: Vec<(usize, usize)> = Vec::new();
+		for (table, curve, _) in self.selected_keyframes.iter() {
+			if !self.tables[*table].1.read_only && !self.is_cyclic((*table, *curve)) && !curves.contains(&(*table, *curve)) {
+				curves.push((*table, *curve));
+			}
+		}
+		let (lower_bound, upper_bound) = curves.iter().fold((-f32::INFINITY, f32::INFINITY), |(lower, upper), (table, curve)| {
+			let (curve_lower, curve_upper) = keyframe_offset_bounds(&self.tables[*table].0.curves[*curve], &selected_in_curve(&self.selected_keyframes, (*table, *curve)));
+			(lower.max(curve_lower), upper.min(curve_upper))
+		});
+		let clamp = |offset: Vec2| Vec2::new(offset.x.max(lower_bound).min(upper_bound), offset.y);
+		let offset = clamp(offset);
+
+		//Like when dragging, the primary keyframe snaps to the grid and the rest keeps its distance to it
+		let available_curves = get_available_curves(&self.tables);
+		let primary = self.selected_keyframes.last().copied().filter(|(table, curve, _)| curves.contains(&(*table, *curve)));
+		let offset = match (primary, grid) {
+			(Some((table, curve, pnt)), Some(grid)) => {
+				let curve = &self.tables[table].0.curves[curve];
+				let snapped = calculate_new_keyframe_pos(pnt, curve, &offset, &available_curves, &self.snap_mode, Some(grid));
+				clamp(Vec2::from(snapped) - Vec2::from(curve.keyframes[pnt].pos))
+			}
+			_ => offset
+		};
+		let moved = curves.iter().map(|(table, curve)| {
+			let keyframes = selected_in_curve(&self.selected_keyframes, (*table, *curve));
+			let positions = keyframes.iter().map(|pnt| calculate_new_keyframe_pos(*pnt, &self.tables[*table].0.curves[*curve], &offset, &available_curves, &self.snap_mode, None)).collect::<Vec<(f32, f32)>>();
+			(*table, *curve, keyframes, positions)
+		}).collect::<Vec<_>>();
+
+		for (table, curve, keyframes, positions) in moved {
+			for (pnt, pos) in keyframes.into_iter().zip(positions) {
+				self.tables[table].0.curves[curve].keyframes[pnt].pos = pos;
+			}
+			self.tables[table].1.dirty = true;
+		}
+	}
+
+	//Walks through the keyframes of all plotted curves in order, wrapping around at the ends
+	fn step_keyframe(&mut self, backwards: bool) {
+		let keyframes = self.curves_to_show.iter().filter(|curve| !self.is_cyclic(**curve)).flat_map(|(table, curve)| {
+			(0..self.tables[*table].0.curves[*curve].keyframes.len()).map(move |pnt| (*table, *curve, pnt))
+		}).collect::<Vec<(usize, usize, usize)>>();
+		if keyframes.is_empty() {
+			return;
+		}
+		let current = self.selected_keyframes.last().and_then(|selected| keyframes.iter().position(|keyframe| keyframe == selected));
+		let next = match (current, backwards) {
+			(Some(current), false) => (current + 1) % keyframes.len(),
+			(Some(current), true) => (current + keyframes.len() - 1) % keyframes.len(),
+			(None, false) => 0,
+			(None, true) => keyframes.len() - 1
+		};
+		select_only(&mut self.selected_keyframes, keyframes[next]);
+	}
+
+	//Jumps to the first keyframe of the next or previous shown curve
+	fn step_curve(&mut self, backwards: bool) {
+		let curves = self.curves_to_show.iter().copied().filter(|(table, curve)| !self.tables[*table].0.curves[*curve].keyframes.is_empty() && !self.is_cyclic((*table, *curve))).collect::<Vec<(usize, usize)>>();
+		if curves.is_empty() {
+			return;
+		}
+		let current = self.selected_keyframes.last().and_then(|(table, curve, _)| curves.iter().position(|other| *other == (*table, *curve)));
+		let next = match (current, backwards) {
+			(Some(current), false) => (current + 1) % curves.len(),
+			(Some(current), true) => (current + curves.len() - 1) % curves.len(),
+			(None, false) => 0,
+			(None, true) => curves.len() - 1
+		};
+		select_only(&mut self.selected_keyframes, (curves[next].0, curves[next].1, 0));
+	}
+
+	//Curves keep at least two keyframes, like when deleting with Ctrl+Right click
+	fn delete_selected_keyframes(&mut self) {
+		let Some(primary) = self.selected_keyframes.last().copied() else { return; };
+		let mut curves: Vec<(usize, usize)> = Vec::new();
+		for (table, curve, _) in self.selected_keyframes.iter() {
+			if !self.tables[*table].1.read_only && !curves.contains(&(*table, *curve)) {
+				curves.push((*table, *curve));
+			}
+		}
+		if let Some((table, curve)) = curves.iter().find(|(table, curve)| {
+			self.tables[*table].0.curves[*curve].keyframes.len() < selected_in_curve(&self.selected_keyframes, (*table, *curve)).len() + 2
+		}) {
+			self.notes.push((Note {
+				text: format!("Cannot delete keyframes: {} needs at least two keyframes!", self.tables[*table].0.curves[*curve].name),
+				severity: NoteSeverity::Error,
+				timeout: 5f32
+			}, None));
+			return;
+		}
+
+		for (table, curve) in curves {
+			for pnt in selected_in_curve(&self.selected_keyframes, (table, curve)).into_iter().rev() {
+				self.tables[table].0.curves[curve].keyframes.remove(pnt);
+				keyframe_removed(&mut self.selected_keyframes, (table, curve), pnt);
+			}
+			self.tables[table].1.dirty = true;
+		}
+
+		//The keyframe that took the place of the deleted one is selected next, so Delete can be pressed repeatedly
+		let (table, curve, pnt) = primary;
+		if self.selected_keyframes.is_empty() && !self.tables[table].1.read_only {
+			let num_keyframes = self.tables[table].0.curves[curve].keyframes.len();
+			select_only(&mut self.selected_keyframes, (table, curve, pnt.min(num_keyframes - 1)));
+		}
+	}
+
+	//Adds a keyframe on the curve halfway to the next keyframe, or one coarse step beyond the last one
+	pub(crate) fn insert_keyframe_after_selected(&mut self) {
+		let Some((table, curve, pnt)) = self.selected_keyframes.last().copied() else { return; };
+		//Cyclic curves can't be evaluated for the new keyframe's Y
+		if self.tables[table].1.read_only || self.is_cyclic((table, curve)) {
+			return;
+		}
+		let keyframes = &self.tables[table].0.curves[curve].keyframes;
+		let x = match keyframes.get(pnt + 1) {
+			Some(next) => (keyframes[pnt].pos.0 + next.pos.0) / 2f32,
+			None => keyframes[pnt].pos.0 + self.nudge_steps.coarse.max(KEYFRAME_MIN_X_DISTANCE)
+		};
+		if keyframes.get(pnt + 1).is_some_and(|next| next.pos.0 - keyframes[pnt].pos.0 < 2f32 * KEYFRAME_MIN_X_DISTANCE) {
+			self.notes.push((Note {
+				text: "Cannot insert a keyframe: There is no room before the next keyframe!".to_string(),
+				severity: NoteSeverity::Error,
+				timeout: 5f32
+			}, None));
+			return;
+		}
+		let y = self.tables[table].0.curves[curve].calculate(x, &get_available_curves(&self.tables));
+
+		self.tables[table].0.curves[curve].keyframes.insert(pnt + 1, CurveKeyframe::new((x, y), CurveSegment::Constant));
+		self.tables[table].1.dirty = true;
+		keyframe_inserted(&mut self.selected_keyframes, (table, curve), pnt + 1);
+		select_only(&mut self.selected_keyframes, (table, curve, pnt + 1));
+	}
+}
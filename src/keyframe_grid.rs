@@ -0,0 +1,353 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use egui::{Event, Id, Style, Ui};
+use fso_tables_impl::curves::{CurveKeyframe, CurveSegment};
+use crate::CurvEdit;
+use crate::note_bar::{Note, NoteSeverity};
+use crate::plot_panel::KEYFRAME_MIN_X_DISTANCE;
+use crate::selection::curve_cleared;
+
+const COLUMN_X: usize = 0;
+const COLUMN_Y: usize = 1;
+const COLUMN_SEGMENT: usize = 2;
+const COLUMN_DEGREE: usize = 3;
+const COLUMN_EASE_IN: usize = 4;
+const TEXT_COLUMNS: [usize; 3] = [COLUMN_X, COLUMN_Y, COLUMN_DEGREE];
+
+//All keyframes of one curve as editable text, written back to the curve as a whole when applied
+pub(crate) struct KeyframeGrid {
+	file: PathBuf,
+	curve: String,
+	rows: Vec<GridRow>,
+	//Until the rows are edited, they follow changes made to the curve elsewhere
+	edited: bool
+}
+
+#[derive(Clone)]
+struct GridRow {
+	x: String,
+	y: String,
+	segment: CurveSegment,
+	degree: String
+}
+
+impl GridRow {
+	fn from_keyframe(keyframe: &CurveKeyframe) -> GridRow {
+		GridRow {
+			x: format!("{}", keyframe.pos.0),
+			y: format!("{}", keyframe.pos.1),
+			segment: keyframe.segment.clone(),
+			degree: match &keyframe.segment {
+				CurveSegment::Polynomial { degree, .. } => format!("{}", degree),
+				_ => String::new()
+			}
+		}
+	}
+
+	fn to_keyframe(&self, row_num: usize) -> Result<CurveKeyframe, String> {
+		let x = f32::from_str(self.x.trim()).map_err(|_| format!("Row {} has an invalid X value", row_num + 1))?;
+		let y = f32::from_str(self.y.trim()).map_err(|_| format!("Row {} has an invalid Y value", row_num + 1))?;
+		let segment = match &self.segment {
+			CurveSegment::Polynomial { ease_in, .. } => {
+				let degree = f32::from_str(self.degree.trim()).ok().filter(|degree| *degree > 0f32)
+					.ok_or(format!("Row {} has an invalid degree, it must be greater than 0", row_num + 1))?;
+				CurveSegment::Polynomial { degree, ease_in: *ease_in }
+			}
+			segment => segment.clone()
+		};
+		Ok(CurveKeyframe { pos: (x, y), segment })
+	}
+}
+
+fn cell_id(row: usize, column: usize) -> Id {
+	Id::new(("keyframe_grid_cell", row, column))
+}
+
+fn segment_name(segment: &CurveSegment) -> &'static str {
+	match segment {
+		CurveSegment::Constant => { "Constant" }
+		CurveSegment::Linear => { "Linear" }
+		CurveSegment::Polynomial { .. } => { "Polynomial" }
+		CurveSegment::Circular { .. } => { "Circular" }
+		CurveSegment::Subcurve { .. } => { "Subcurve" }
+	}
+}
+
+//Fills cells from pasted spreadsheet text, one line per row and tab, comma or space separated columns, adding rows as needed
+fn paste_cells(rows: &mut Vec<GridRow>, row: usize, column: usize, text: &str) {
+	let lines = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<&str>>();
+	for (line_num, line) in lines.iter().enumerate() {
+		let row_num = row + line_num;
+		if row_num >= rows.len() {
+			rows.push(GridRow { x: String::new(), y: String::new(), segment: CurveSegment::Linear, degree: String::new() });
+		}
+		let grid_row = &mut rows[row_num];
+		let fields = line.split(['\t', ',', ' ', ';']).filter(|field| !field.is_empty());
+		for (column, field) in (column..=COLUMN_EASE_IN).zip(fields) {
+			match column {
+				COLUMN_X => { grid_row.x = field.to_string(); }
+				COLUMN_Y => { grid_row.y = field.to_string(); }
+				COLUMN_SEGMENT => {
+					let ease_in = match &grid_row.segment {
+						CurveSegment::Polynomial { ease_in, .. } | CurveSegment::Circular { ease_in } => *ease_in,
+						_ => None
+					};
+					grid_row.segment = match field.to_ascii_lowercase().as_str() {
+						"constant" => CurveSegment::Constant,
+						"linear" => CurveSegment::Linear,
+						"polynomial" => CurveSegment::Polynomial { degree: 2f32, ease_in },
+						"circular" => CurveSegment::Circular { ease_in },
+						_ => grid_row.segment.clone()
+					};
+				}
+				COLUMN_DEGREE => { grid_row.degree = field.to_string(); }
+				_ => {
+					let ease_in = match field.to_ascii_lowercase().as_str() {
+						"true" | "yes" | "1" => Some(true),
+						"false" | "no" | "0" => Some(false),
+						_ => None
+					};
+					match &mut grid_row.segment {
+						CurveSegment::Polynomial { ease_in: row_ease_in, .. } | CurveSegment::Circular { ease_in: row_ease_in } if ease_in.is_some() => { *row_ease_in = ease_in; }
+						_ => {}
+					}
+				}
+			}
+		}
+	}
+}
+
+impl CurvEdit {
+	//The curve being worked on: The one of the keyframe in the keyframe panel, else the last one shown
+	pub(crate) fn selected_curve(&self) -> Option<(usize, usize)> {
+		self.selected_keyframes.last().map(|(table, curve, _)| (*table, *curve)).or(self.curves_to_show.last().copied())
+	}
+
+	pub(crate) fn open_keyframe_grid(&mut self) {
+		let Some((table, curve)) = self.selected_curve() else { return; };
+		let curve = &self.tables[table].0.curves[curve];
+		self.keyframe_grid = Some(KeyframeGrid {
+			file: self.tables[table].1.file.clone(),
+			curve: curve.name.clone(),
+			rows: curve.keyframes.iter().map(GridRow::from_keyframe).collect(),
+			edited: false
+		});
+	}
+
+	pub(crate) fn keyframe_grid_dialog(&mut self, ctx: &egui::Context) {
+		let Some(grid) = &mut self.keyframe_grid else { return; };
+		let Some(table_num) = self.tables.iter().position(|(_, file_data)| file_data.file == grid.file) else {
+			self.keyframe_grid = None;
+			return;
+		};
+		let Some(curve_num) = self.tables[table_num].0.curves.iter().position(|curve| curve.name == grid.curve) else {
+			self.keyframe_grid = None;
+			return;
+		};
+		let read_only = self.tables[table_num].1.read_only;
+		if !grid.edited {
+			grid.rows = self.tables[table_num].0.curves[curve_num].keyframes.iter().map(GridRow::from_keyframe).collect();
+		}
+
+		//Pasting several lines or columns into a cell spreads them over the cells below and to the right
+		let focused = ctx.memory(|mem| mem.focused());
+		let focused_cell = focused.and_then(|focused| (0..grid.rows.len()).flat_map(|row| TEXT_COLUMNS.map(|column| (row, column))).find(|(row, column)| cell_id(*row, *column) == focused));
+		if let Some((row, column)) = focused_cell {
+			let pasted = ctx.input_mut(|i| {
+				let idx = i.events.iter().position(|event| matches!(event, Event::Paste(text) if text.trim().contains(['\n', '\t'])))?;
+				Some(i.events.remove(idx))
+			});
+			if let Some(Event::Paste(text)) = pasted {
+				paste_cells(&mut grid.rows, row, column, &text);
+				grid.edited = true;
+			}
+		}
+
+		let mut open = true;
+		let mut apply = false;
+		let mut revert = false;
+		egui::Window::new(format!("Keyframes of {}", grid.curve))
+			.id(Id::new("keyframe_grid"))
+			.collapsible(false)
+			.resizable(true)
+			.open(&mut open)
+			.show(ctx, |ui| {
+				ui.label(grid.file.file_name().unwrap_or("".as_ref()).to_string_lossy());
+				if read_only {
+					ui.label("🔒 Reference table, read-only.");
+					ui.set_enabled(false);
+				}
+				ui.add_space(4f32);
+				egui::ScrollArea::vertical().max_height(400f32).show(ui, |ui| {
+					grid_rows(ui, &mut grid.rows, &mut grid.edited, &self.noto_symbols_buttons);
+				});
+				ui.add_space(4f32);
+				ui.horizontal(|ui| {
+					if ui.button("+ Add keyframe").clicked() {
+						let last = grid.rows.last().cloned();
+						grid.rows.push(GridRow {
+							x: last.as_ref().and_then(|row| f32::from_str(row.x.trim()).ok()).map_or("0".to_string(), |x| format!("{}", x + 1f32)),
+							y: last.as_ref().map_or("0".to_string(), |row| row.y.clone()),
+							segment: CurveSegment::Linear,
+							degree: String::new()
+						});
+						grid.edited = true;
+					}
+					ui.add_enabled_ui(grid.edited, |ui| {
+						apply = ui.button("Apply").on_hover_text("Sort the rows by X and write them to the curve.").clicked();
+						revert = ui.button("Revert").on_hover_text("Discard the edits and show the curve as it is.").clicked();
+					});
+				});
+			});
+
+		if !open {
+			self.keyframe_grid = None;
+		}
+		else if revert {
+			grid.edited = false;
+		}
+		else if apply {
+			self.apply_keyframe_grid(table_num, curve_num);
+		}
+	}
+
+	fn apply_keyframe_grid(&mut self, table_num: usize, curve_num: usize) {
+		let Some(grid) = &mut self.keyframe_grid else { return; };
+		let keyframes = grid.rows.iter().enumerate().map(|(row_num, row)| row.to_keyframe(row_num)).collect::<Result<Vec<CurveKeyframe>, String>>();
+		let mut keyframes = match keyframes {
+			Ok(keyframes) => keyframes,
+			Err(error) => {
+				self.notes.push((Note {
+					text: format!("Cannot apply keyframes: {}!", error),
+					severity: NoteSeverity::Error,
+					timeout: 5f32
+				}, None));
+				return;
+			}
+		};
+		if keyframes.len() < 2 {
+			self.notes.push((Note {
+				text: "Cannot apply keyframes: A curve needs at least two keyframes!".to_string(),
+				severity: NoteSeverity::Error,
+				timeout: 5f32
+			}, None));
+			return;
+		}
+		keyframes.sort_by(|a, b| a.pos.0.total_cmp(&b.pos.0));
+		if let Some(pair) = keyframes.windows(2).find(|pair| pair[1].pos.0 - pair[0].pos.0 < KEYFRAME_MIN_X_DISTANCE) {
+			self.notes.push((Note {
+				text: format!("Cannot apply keyframes: Keyframes at X {} and {} are closer than {}!", pair[0].pos.0, pair[1].pos.0, KEYFRAME_MIN_X_DISTANCE),
+				severity: NoteSeverity::Error,
+				timeout: 5f32
+			}, None));
+			return;
+		}
+
+		grid.rows = keyframes.iter().map(GridRow::from_keyframe).collect();
+		grid.edited = false;
+		//Keyframes may have been added, removed or reordered, so the old indices mean nothing anymore
+		curve_cleared(&mut self.selected_keyframes, (table_num, curve_num));
+		self.tables[table_num].0.curves[curve_num].keyframes = keyframes;
+		self.tables[table_num].1.dirty = true;
+	}
+}
+
+fn grid_rows(ui: &mut Ui, rows: &mut Vec<GridRow>, edited: &mut bool, button_style: &Arc<Style>) {
+	let mut insert: Option<usize> = None;
+	let mut remove: Option<usize> = None;
+	egui::Grid::new("keyframe_grid_rows").striped(true).num_columns(7).show(ui, |ui| {
+		ui.label("");
+		ui.label("X");
+		ui.label("Y");
+		ui.label("Interpolation");
+		ui.label("Degree");
+		ui.label("Ease in");
+		ui.label("");
+		ui.end_row();
+
+		for (row_num, row) in rows.iter_mut().enumerate() {
+			ui.label(format!("{}", row_num + 1));
+			*edited |= ui.add(egui::TextEdit::singleline(&mut row.x).id(cell_id(row_num, COLUMN_X)).desired_width(70f32)).changed();
+			*edited |= ui.add(egui::TextEdit::singleline(&mut row.y).id(cell_id(row_num, COLUMN_Y)).desired_width(70f32)).changed();
+
+			let ease_in = match &row.segment {
+				CurveSegment::Polynomial { ease_in, .. } | CurveSegment::Circular { ease_in } => *ease_in,
+				_ => None
+			};
+			//Switching to the type the row already has keeps its settings
+			let polynomial = if let CurveSegment::Polynomial { .. } = row.segment { row.segment.clone() } else { CurveSegment::Polynomial { degree: 2f32, ease_in } };
+			let circular = if let CurveSegment::Circular { .. } = row.segment { row.segment.clone() } else { CurveSegment::Circular { ease_in } };
+			let mut segment = row.segment.clone();
+			egui::ComboBox::from_id_source(("keyframe_grid_segment", row_num))
+				.selected_text(segment_name(&segment))
+				.width(90f32)
+				.show_ui(ui, |ui| {
+					ui.selectable_value(&mut segment, CurveSegment::Constant, "Constant");
+					ui.selectable_value(&mut segment, CurveSegment::Linear, "Linear");
+					ui.selectable_value(&mut segment, polynomial, "Polynomial");
+					ui.selectable_value(&mut segment, circular, "Circular");
+				}).response.on_hover_text(match &row.segment {
+					CurveSegment::Subcurve { curve } => format!("Follows {}, pick another source curve in the keyframe panel.", curve),
+					_ => "Interpolation towards the next keyframe.".to_string()
+				});
+			if segment != row.segment {
+				if matches!(segment, CurveSegment::Polynomial { .. }) && row.degree.is_empty() {
+					row.degree = "2".to_string();
+				}
+				row.segment = segment;
+				*edited = true;
+			}
+
+			if let CurveSegment::Polynomial { .. } = row.segment {
+				*edited |= ui.add(egui::TextEdit::singleline(&mut row.degree).id(cell_id(row_num, COLUMN_DEGREE)).desired_width(40f32)).changed();
+			}
+			else {
+				ui.label("");
+			}
+			match &mut row.segment {
+				CurveSegment::Polynomial { ease_in, .. } | CurveSegment::Circular { ease_in } => {
+					let mut checked = ease_in.unwrap_or(true);
+					if ui.checkbox(&mut checked, "").changed() {
+						*ease_in = Some(checked);
+						*edited = true;
+					}
+				}
+				_ => { ui.label(""); }
+			}
+
+			ui.horizontal(|ui| {
+				ui.set_style(button_style.clone());
+				if ui.button("🡑").on_hover_text("Insert keyframe above.").clicked() {
+					insert = Some(row_num);
+				}
+				if ui.button("🡓").on_hover_text("Insert keyframe below.").clicked() {
+					insert = Some(row_num + 1);
+				}
+				if ui.button("🗑").on_hover_text("Delete keyframe.").clicked() {
+					remove = Some(row_num);
+				}
+			});
+			ui.end_row();
+		}
+	});
+
+	//New keyframes go halfway between their neighbours, or one unit beyond the first or last keyframe
+	if let Some(row_num) = insert {
+		let position = |row: Option<&GridRow>| row.and_then(|row| Some((f32::from_str(row.x.trim()).ok()?, f32::from_str(row.y.trim()).ok()?)));
+		let before = position(row_num.checked_sub(1).and_then(|row_num| rows.get(row_num)));
+		let after = position(rows.get(row_num));
+		let (x, y) = match (before, after) {
+			(Some(before), Some(after)) => ((before.0 + after.0) / 2f32, (before.1 + after.1) / 2f32),
+			(Some(before), None) => (before.0 + 1f32, before.1),
+			(None, Some(after)) => (after.0 - 1f32, after.1),
+			(None, None) => (0f32, 0f32)
+		};
+		rows.insert(row_num, GridRow { x: format!("{}", x), y: format!("{}", y), segment: CurveSegment::Linear, degree: String::new() });
+		*edited = true;
+	}
+	if let Some(row_num) = remove {
+		rows.remove(row_num);
+		*edited = true;
+	}
+}
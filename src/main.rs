@@ -5,20 +5,42 @@ mod context_bar;
 mod modifier_panel;
 mod curves_panel;
 mod note_bar;
+mod history;
+mod check;
+mod dependencies;
+mod selection;
+mod clipboard;
+mod close_dialog;
+mod disk_watch;
+mod references;
+mod vp;
+mod session;
+mod recent;
+mod keyframe_grid;
+mod keyboard;
+mod sample_cache;
 
 use fso_tables_impl::curves::CurveTable;
+use std::collections::HashSet;
 use std::error::Error;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 use eframe::egui;
 use eframe::epaint::FontFamily;
-use egui::{FontData, FontDefinitions, FontId, FontTweak, Frame, Margin, Style, TextStyle};
+use egui::{Event, FontData, FontDefinitions, FontId, FontTweak, Frame, Margin, Style, TextStyle};
 use homedir::get_my_home;
 use include_dir::{Dir, include_dir};
-use crate::curves_panel::SnapMode;
-use crate::modifier_panel::{KEYFRAME_PANEL_HEIGHT, MODIFIER_PANEL_WIDTH};
+use crate::context_bar::{SAVE_AS_SHORTCUT, SAVE_SHORTCUT};
+use crate::curves_panel::{DisplayMode, Extrapolation, SnapMode, SnapTargets};
+use crate::history::{History, REDO_SHORTCUT, UNDO_SHORTCUT};
+use crate::keyboard::NudgeSteps;
+use crate::keyframe_grid::KeyframeGrid;
+use crate::modifier_panel::{DeleteCurveDialog, KEYFRAME_PANEL_HEIGHT, MODIFIER_PANEL_MAX_WIDTH, MODIFIER_PANEL_WIDTH};
 use crate::note_bar::Note;
+use crate::recent::RecentEntry;
+use crate::sample_cache::SampleCache;
+use crate::vp::VpDialog;
 
 const CURVEDIT_VERSION: &str = env!("CARGO_PKG_VERSION");
 static ASSET_DIR: Dir = include_dir!("src/assets");
@@ -26,14 +48,27 @@ static ASSET_DIR: Dir = include_dir!("src/assets");
 fn main() -> Result<(), Box<dyn Error>> {
 	let mut args = std::env::args();
 	args.next();
+	let first_arg = args.next();
+	if first_arg.as_deref() == Some("check") {
+		std::process::exit(check::check_tables(args));
+	}
+	
+	//Absolute paths, so tables opened from a terminal compare equal to ones opened through dialogs or the session.
+	//Not canonicalized, as that gives \\?\ paths on Windows which the dialogs never do.
+	let paths = first_arg.into_iter().chain(args).map(|arg| std::path::absolute(&arg).unwrap_or(PathBuf::from(arg))).collect::<Vec<PathBuf>>();
 	let path = 
-		if let Some(path) = args.next().map(|arg| PathBuf::from(arg.as_str())) { path }
+		if let Some(path) = paths.first().map(|path| if path.is_dir() { path.clone() } else { path.parent().unwrap_or(path).to_path_buf() }) { path }
 		else if let Ok(Some(path)) = get_my_home() { path }
 		else { PathBuf::from("") };
 	
+	let session = session::load_session();
+	let mut viewport = egui::ViewportBuilder::default()
+		.with_inner_size(session.window_size.unwrap_or(egui::vec2(1080.0, 720.0)));
+	if let Some(window_pos) = session.window_pos {
+		viewport = viewport.with_position(window_pos);
+	}
 	let options = eframe::NativeOptions {
-		viewport: egui::ViewportBuilder::default()
-			.with_inner_size([1080.0, 720.0]),
+		viewport,
 		..Default::default()
 	};
 	
@@ -61,6 +96,14 @@ fn main() -> Result<(), Box<dyn Error>> {
 			let mut style = (*ctx.egui_ctx.style()).clone();
 			style.text_styles.insert(TextStyle::Button, FontId::new(11f32, FontFamily::Name("NotoSymbols2".into())));
 			curvedit.noto_symbols_buttons = Arc::new(style);
+
+			curvedit.restore_session(session);
+			//Tables from the last session may already include some of the arguments
+			for path in paths {
+				if !curvedit.tables.iter().any(|(_, file_data)| file_data.file == path) {
+					curvedit.open_path(path);
+				}
+			}
 			
 			curvedit
 		})),
@@ -70,7 +113,13 @@ fn main() -> Result<(), Box<dyn Error>> {
 #[derive(Default)]
 struct TableData {
 	file: PathBuf,
-	dirty: bool
+	dirty: bool,
+	//When the file was last read or written by us
+	modified: Option<SystemTime>,
+	//The version on disk, if it changed while we had unsaved changes
+	on_disk: Option<CurveTable>,
+	//Reference tables of mods we depend on, these are never edited or saved
+	read_only: bool
 }
 
 #[derive(Default)]
@@ -78,21 +127,81 @@ struct CurvEdit {
 	tables: Vec<(CurveTable, TableData)>,
 	curves_to_show: Vec<(usize, usize)>,
 	notes: Vec<(Note, Option<Instant>)>,
-	selected_keyframe: Option<(usize, usize, usize)>,
+	selected_keyframes: Vec<(usize, usize, usize)>,
 	snap_mode: SnapMode,
+	display_mode: DisplayMode,
 	default_path: PathBuf,
-	noto_symbols_buttons: Arc<Style>
+	noto_symbols_buttons: Arc<Style>,
+	history: History,
+	cyclic_curves: HashSet<String>,
+	delete_dialog: Option<DeleteCurveDialog>,
+	window_title: String,
+	close_dialog: bool,
+	allow_close: bool,
+	last_disk_check: Option<Instant>,
+	disk_diff: Option<PathBuf>,
+	vp_dialog: Option<VpDialog>,
+	modifier_panel_width: f32,
+	recent: Vec<RecentEntry>,
+	keyframe_grid: Option<KeyframeGrid>,
+	nudge_steps: NudgeSteps,
+	snap_targets: SnapTargets,
+	samples: SampleCache,
+	extrapolation: Extrapolation,
+	//The input entered to evaluate all shown curves at
+	evaluate_at: Option<f32>
 }
 struct CurvEditInput {
 	pointer_down: bool,
+	pointer_pressed: bool,
 	right_clicked: bool,
 	ctrl_held: bool,
-	escape_pressed: bool
+	shift_held: bool,
+	escape_pressed: bool,
+	insert_pressed: bool
 }
 
 impl eframe::App for CurvEdit {
 	fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-		//TODO v1.2 Undo-System
+		self.intercept_close(ctx);
+		if ctx.input(|i| i.viewport().close_requested()) && !self.close_dialog {
+			self.save_session(ctx);
+		}
+		let mut tab_navigation = false;
+		//Nothing may be edited behind the unsaved changes dialog, as Save All would write it
+		if !self.close_dialog {
+			if ctx.memory(|mem| mem.focused().is_none()) {
+				if ctx.input_mut(|i| i.consume_shortcut(&REDO_SHORTCUT)) {
+					self.redo();
+				}
+				else if ctx.input_mut(|i| i.consume_shortcut(&UNDO_SHORTCUT)) {
+					self.undo();
+				}
+
+				let clipboard_events = ctx.input(|i| i.events.iter().filter(|event| matches!(event, Event::Copy | Event::Paste(_))).cloned().collect::<Vec<Event>>());
+				for event in clipboard_events {
+					match event {
+						Event::Copy => { self.copy(ctx); }
+						Event::Paste(text) => { self.paste(&text, ctx.input(|i| i.modifiers.command && i.modifiers.shift)); }
+						_ => {}
+					}
+				}
+				tab_navigation = self.keyboard_editing(ctx);
+			}
+			if ctx.input_mut(|i| i.consume_shortcut(&SAVE_AS_SHORTCUT)) {
+				self.save_current_table_as();
+			}
+			else if ctx.input_mut(|i| i.consume_shortcut(&SAVE_SHORTCUT)) {
+				self.save_current_table();
+			}
+			let dropped_files = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|file| file.path.clone()).collect::<Vec<PathBuf>>());
+			for path in dropped_files {
+				self.open_path(path);
+			}
+		}
+		self.check_files_on_disk(ctx);
+		self.check_subcurve_cycles();
+		self.samples.refresh(&self.tables);
 		
 		egui::TopBottomPanel::top("context_bar").show(ctx, |ui| self.context_bar(ui));
 		egui::TopBottomPanel::bottom("note_bar").show(ctx, |ui| self.note_bar(ui, ctx));
@@ -102,14 +211,20 @@ impl eframe::App for CurvEdit {
 			top: 0.0,
 			bottom: 4.0,
 		})).show(ctx, |ui| {
-			egui::SidePanel::right("modifier_panel").frame(Frame::default().inner_margin(0f32)).resizable(false).exact_width(MODIFIER_PANEL_WIDTH).show_inside(ui, |ui| {
-				egui::TopBottomPanel::bottom("keyframe_panel").exact_height(KEYFRAME_PANEL_HEIGHT).show_inside(ui, |ui| self.current_keyframe(ui, ctx));
-				egui::CentralPanel::default().show_inside(ui, |ui| {
-					egui::ScrollArea::vertical().show(ui, |ui| {
-						self.curve_list(ui, ctx);
+			let modifier_panel = egui::SidePanel::right("modifier_panel")
+				.frame(Frame::default().inner_margin(0f32))
+				.resizable(true)
+				.width_range(MODIFIER_PANEL_WIDTH..=MODIFIER_PANEL_MAX_WIDTH)
+				.default_width(self.modifier_panel_width.max(MODIFIER_PANEL_WIDTH))
+				.show_inside(ui, |ui| {
+					egui::TopBottomPanel::bottom("keyframe_panel").exact_height(KEYFRAME_PANEL_HEIGHT).show_inside(ui, |ui| self.current_keyframe(ui, ctx));
+					egui::CentralPanel::default().show_inside(ui, |ui| {
+						egui::ScrollArea::vertical().show(ui, |ui| {
+							self.curve_list(ui, ctx);
+						});
 					});
 				});
-			});
+			self.modifier_panel_width = modifier_panel.response.rect.width();
 			egui::CentralPanel::default().frame(Frame::default().inner_margin(0f32)).show_inside(ui, |ui| {
 				egui::TopBottomPanel::top("mode_panel").show_inside(ui, |ui| {
 					ui.vertical(|ui| {
@@ -121,6 +236,25 @@ impl eframe::App for CurvEdit {
 				egui::CentralPanel::default().show_inside(ui, |ui| self.curve_panel(ui, ctx));
 			});
 		});
+		//The other dialogs would sit above the backdrop of the unsaved changes dialog, so they wait until it is gone
+		if !self.close_dialog {
+			self.delete_curve_dialog(ctx);
+			self.disk_diff_dialog(ctx);
+			self.vp_archive_dialog(ctx);
+			self.keyframe_grid_dialog(ctx);
+		}
+		self.unsaved_tables_dialog(ctx);
+		
+		if tab_navigation {
+			ctx.memory_mut(|mem| mem.stop_text_input());
+		}
+		
+		//Curves edited after plotting would otherwise leave the curves using them stale until something else repaints
+		if self.samples.refresh(&self.tables) {
+			ctx.request_repaint();
+		}
+		self.record_history();
+		self.update_window_title(ctx);
 	}
 }
 
@@ -1,25 +1,38 @@
-use std::fs;
+use std::collections::{HashMap, HashSet};
 use std::mem::swap;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Instant;
-use egui::{Align, Id, Layout, Style, Ui};
+use egui::{Align, Align2, Color32, Id, Layout, Style, Ui, Vec2};
 use fso_tables_impl::curves::{BUILTIN_CURVES, Curve, CurveKeyframe, CurveSegment, CurveTable};
 use native_dialog::{MessageDialog, MessageType};
 use crate::{CurvEdit, TableData};
+use crate::context_bar::save_table;
+use crate::dependencies::DependencyGraph;
+use crate::disk_watch::DiskAction;
 use crate::note_bar::{Note, NoteSeverity};
+use crate::plot_panel::{get_available_curves, KEYFRAME_MIN_X_DISTANCE, name_taken};
+use crate::selection::{curve_cleared, curve_removed, curves_swapped, selected_in_curve, table_removed};
 
 pub(crate) const MODIFIER_PANEL_WIDTH: f32 = 300f32;
+pub(crate) const MODIFIER_PANEL_MAX_WIDTH: f32 = 600f32;
 pub(crate) const KEYFRAME_PANEL_HEIGHT: f32 = 300f32;
 
 pub(crate) const CURVE_LABEL_HEIGHT: f32 = 22f32;
 
+const BAKE_SAMPLES: usize = 32;
+
 impl CurvEdit {
 	pub(crate) fn curve_list<'a>(&mut self, ui: &mut Ui, ctx: &egui::Context) {
 		let mut curves: Vec<(usize, usize)> = Vec::new();
 		let mut remove_table: Option<usize> = None;
 		let mut rename_curves: Vec<(usize, usize, String)> = Vec::new();
 		let mut add_curve: Option<(usize, String)> = None;
+		let mut delete_dialog: Option<DeleteCurveDialog> = None;
+		let mut disk_action: Option<(usize, DiskAction)> = None;
+		let shadowed_by = shadowing_tables(&self.tables);
+		let referenced_curves = self.tables.iter().enumerate().flat_map(|(table_num, (table, _))| (0..table.curves.len()).map(move |curve_num| (table_num, curve_num)))
+			.filter(|curve| !dangling_references(&self.tables, *curve).is_empty()).collect::<HashSet<(usize, usize)>>();
 
 		for (table_num, (table, file_data)) in self.tables.iter_mut().enumerate() {
 			ui.horizontal(|ui| {
@@ -28,17 +41,55 @@ impl CurvEdit {
 					remove_table = Some(table_num);
 				}
 			});
+			if file_data.on_disk.is_some() {
+				ui.horizontal(|ui| {
+					ui.set_height(CURVE_LABEL_HEIGHT);
+					ui.add_space(20f32);
+					ui.colored_label(Color32::from_rgb(255, 191, 64), "Changed on disk!");
+					ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+						if ui.small_button("Diff").on_hover_text("Compare the curves here with the version on disk.").clicked() {
+							disk_action = Some((table_num, DiskAction::ShowDiff));
+						}
+						if ui.small_button("Keep ours").on_hover_text("Keep the changes made here, saving will overwrite the file.").clicked() {
+							disk_action = Some((table_num, DiskAction::KeepOurs));
+						}
+						if ui.small_button("Reload").on_hover_text("Discard the changes made here and use the version on disk.").clicked() {
+							disk_action = Some((table_num, DiskAction::Reload));
+						}
+					});
+				});
+			}
 
 			let mut remove_curve: Option<usize> = None;
 			let mut switch_curves: Option<(usize, usize)> = None;
 			for (curve_num, curve) in table.curves.iter().enumerate() {
 				let is_clicked = self.curves_to_show.contains(&(table_num, curve_num));
+				if file_data.read_only {
+					ui.horizontal(|ui| {
+						ui.set_height(CURVE_LABEL_HEIGHT);
+						if reference_curve_entry(ui, curve, shadowed_by.get(&(table_num, curve_num)).map(String::as_str), is_clicked, &self.noto_symbols_buttons) {
+							curves.push((table_num, curve_num));
+						}
+					});
+					continue;
+				}
 				ui.horizontal(|ui| {
 					ui.set_height(CURVE_LABEL_HEIGHT);
-					let (display, remove, up, down, new_name) = curve_entry(ui, curve, ctx, is_clicked, curve_num < table.curves.len() - 1, curve_num > 0, &self.noto_symbols_buttons);
+					let is_referenced = referenced_curves.contains(&(table_num, curve_num));
+					//Overrides share their name with the curve they replace, so each table keeps its own edit state
+					let (display, remove, up, down, new_name) = ui.push_id(&file_data.file, |ui| curve_entry(ui, curve, shadowed_by.get(&(table_num, curve_num)).map(String::as_str), is_clicked, curve_num < table.curves.len() - 1, curve_num > 0, &self.noto_symbols_buttons)).inner;
 					let mut curve_num_to_display = switch_curves.map_or(curve_num, |(switch, other)| if other == curve_num { switch } else { curve_num });
 
-					if remove {
+					//Curves used as subcurves get their own dialog to deal with the references
+					if remove && is_referenced {
+						delete_dialog = Some(DeleteCurveDialog { table: table_num, curve: curve_num, name: curve.name.clone(), retarget: None });
+					}
+					else if remove && MessageDialog::new()
+						.set_title("Delete curve?")
+						.set_type(MessageType::Warning)
+						.set_text(&format!("Are you sure you want to delete the curve {}?", curve.name))
+						.show_confirm()
+						.unwrap_or(false) {
 						file_data.dirty = true;
 						remove_curve = Some(curve_num);
 					}
@@ -66,17 +117,17 @@ impl CurvEdit {
 			if let Some(to_remove) = remove_curve {
 				table.curves.remove(to_remove);
 				curves = curves.iter().filter(|(table, curve)| *table != table_num || *curve != to_remove).map(|(table, curve)| (*table, if *table == table_num && *curve > to_remove { *curve - 1 } else { *curve })).collect();
-				if let Some((table, curve, _)) = self.selected_keyframe {
-					if table == table_num && curve == to_remove {
-						self.selected_keyframe = None;
-					}
-				}
+				curve_removed(&mut self.selected_keyframes, (table_num, to_remove));
 			}
 			if let Some((first, second)) = switch_curves {
 				let (front, back) = table.curves.split_at_mut(second);
 				swap(&mut front[first], &mut back[0]);
+				curves_swapped(&mut self.selected_keyframes, table_num, first, second);
 			}
 
+			if file_data.read_only {
+				continue;
+			}
 			ui.horizontal(|ui| {
 				ui.add_space(20f32);
 				
@@ -102,19 +153,29 @@ impl CurvEdit {
 			if self.tables[table_num].0.curves[curve_num].name == new_name {
 				continue;
 			}
-			if self.tables.iter().find(|(table, _)| table.curves.iter().find(|curve| curve.name == new_name).is_some()).is_some() {
+			//Curves of tables loading earlier can be overridden like this
+			if name_taken(&self.tables, table_num, &new_name) {
 				self.notes.push((Note {
-					text: format!("Cannot rename {} to {}: Curve with this name already exists!", self.tables[table_num].0.curves[curve_num].name, new_name),
+					text: format!("Cannot rename {} to {}: Curve with this name already exists in this table, a later one or the builtin curves!", self.tables[table_num].0.curves[curve_num].name, new_name),
+					severity: NoteSeverity::Error,
+					timeout: 5f32
+				}, None));
+			}
+			else if renaming_creates_cycle(&self.tables, (table_num, curve_num), &new_name) {
+				self.notes.push((Note {
+					text: format!("Cannot rename {} to {}: Existing subcurve references would become cyclic!", self.tables[table_num].0.curves[curve_num].name, new_name),
 					severity: NoteSeverity::Error,
 					timeout: 5f32
 				}, None));
 			}
 			else {
+				let is_effective = is_effective(&self.tables, (table_num, curve_num));
 				swap(&mut self.tables[table_num].0.curves[curve_num].name, &mut new_name);
 				let old_name = new_name;
 				let new_name = self.tables[table_num].0.curves[curve_num].name.clone();
 				//We also need to find all references to this in subcurves and update them.
-				for (table, file_data) in self.tables.iter_mut() {
+				//References to a shadowed definition's name resolve to the overriding curve, so they stay as they are.
+				for (table, file_data) in self.tables.iter_mut().filter(|(_, file_data)| is_effective && !file_data.read_only) {
 					for curve in table.curves.iter_mut() {
 						for keyframe in curve.keyframes.iter_mut() {
 							match &mut keyframe.segment {
@@ -135,17 +196,13 @@ impl CurvEdit {
 		if let Some(to_remove) = remove_table {
 			self.tables.remove(to_remove);
 			curves = curves.iter().filter(|(table, _)| *table != to_remove).map(|(table, curve)| (if *table > to_remove { *table - 1 } else { *table }, *curve)).collect();
-			if let Some((table, _, _)) = self.selected_keyframe {
-				if table == to_remove {
-					self.selected_keyframe = None;
-				}
-			}
+			table_removed(&mut self.selected_keyframes, to_remove);
 		}
 		
 		if let Some((table, name)) = add_curve {
-			if self.tables.iter().find(|(table, _)| table.curves.iter().find(|curve| curve.name == name).is_some()).is_some() {
+			if name_taken(&self.tables, table, &name) {
 				self.notes.push((Note {
-					text: format!("Cannot add {}: Curve with this name already exists!", name),
+					text: format!("Cannot add {}: Curve with this name already exists in this table, a later one or the builtin curves!", name),
 					severity: NoteSeverity::Error,
 					timeout: 5f32
 				}, None));
@@ -161,7 +218,121 @@ impl CurvEdit {
 			}
 		}
 
+		if delete_dialog.is_some() {
+			self.delete_dialog = delete_dialog;
+		}
+
 		self.curves_to_show = curves;
+
+		if let Some((table_num, action)) = disk_action {
+			//Closing the table this frame takes the conflict with it
+			if remove_table.is_none() {
+				self.resolve_disk_conflict(table_num, action);
+			}
+		}
+	}
+
+	pub(crate) fn delete_curve_dialog(&mut self, ctx: &egui::Context) {
+		let Some(dialog) = &mut self.delete_dialog else { return; };
+		let still_exists = self.tables.get(dialog.table).and_then(|(table, _)| table.curves.get(dialog.curve)).is_some_and(|curve| curve.name == dialog.name);
+		if !still_exists {
+			//The curve moved or vanished while the dialog was open
+			self.delete_dialog = None;
+			return;
+		}
+
+		let references = dangling_references(&self.tables, (dialog.table, dialog.curve));
+
+		let dependencies = DependencyGraph::new(&get_available_curves(&self.tables));
+		let list_of_curves = self.tables.iter().flat_map(|(table, _)| table.curves.iter().map(|curve| curve.name.clone()))
+			.chain(BUILTIN_CURVES.iter().map(|curve| curve.name.clone()))
+			.filter(|other| *other != dialog.name)
+			.collect::<Vec<String>>();
+		let referencing_curves = references.iter().map(|(table, curve, _)| self.tables[*table].0.curves[*curve].name.as_str()).collect::<HashSet<&str>>();
+
+		let mut action: Option<DeleteAction> = None;
+		egui::Window::new("Delete curve?")
+			.collapsible(false)
+			.resizable(false)
+			.anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+			.show(ctx, |ui| {
+				ui.label(format!("The curve {} is used as a subcurve by:", dialog.name));
+				egui::ScrollArea::vertical().max_height(200f32).show(ui, |ui| {
+					for (table, curve, keyframe) in references.iter() {
+						let (table, file_data) = &self.tables[*table];
+						ui.label(format!("{}: {}, keyframe {}", file_data.file.file_name().map_or("".to_string(), |filename| filename.to_string_lossy().to_string()), table.curves[*curve].name, keyframe + 1));
+					}
+				});
+				ui.separator();
+				ui.horizontal(|ui| {
+					ui.label("Retarget to: ");
+					egui::ComboBox::from_id_source("delete_retarget")
+						.selected_text(dialog.retarget.as_deref().unwrap_or(""))
+						.show_ui(ui, |ui| {
+							for other_curve in list_of_curves.iter() {
+								let creates_cycle = referencing_curves.iter().any(|curve| dependencies.would_create_cycle(curve, other_curve));
+								ui.add_enabled_ui(!creates_cycle, |ui| ui.selectable_value(&mut dialog.retarget, Some(other_curve.clone()), other_curve))
+									.inner.on_disabled_hover_text(format!("{} uses one of the referencing curves, this would create a cycle.", other_curve));
+							}
+						});
+				});
+				ui.horizontal(|ui| {
+					if ui.button("Cancel").clicked() {
+						action = Some(DeleteAction::Cancel);
+					}
+					if ui.add_enabled(dialog.retarget.is_some(), egui::Button::new("Retarget and delete")).on_hover_text("Make the referencing keyframes use the selected curve instead.").clicked() {
+						action = dialog.retarget.clone().map(DeleteAction::Retarget);
+					}
+					if ui.button("Bake and delete").on_hover_text("Replace the referencing keyframes with linear keyframes following the current shape.").clicked() {
+						action = Some(DeleteAction::Bake);
+					}
+				});
+			});
+
+		let Some(action) = action else { return; };
+		//Baking evaluates the referencing curves, which never ends for cyclic ones
+		if matches!(action, DeleteAction::Bake) {
+			if let Some((table, curve, _)) = references.iter().find(|(table, curve, _)| self.is_cyclic((*table, *curve))) {
+				self.notes.push((Note {
+					text: format!("Cannot bake {}: It has cyclic subcurve references!", self.tables[*table].0.curves[*curve].name),
+					severity: NoteSeverity::Error,
+					timeout: 5f32
+				}, None));
+				return;
+			}
+		}
+		let Some(DeleteCurveDialog { table: table_num, curve: curve_num, name, .. }) = self.delete_dialog.take() else { return; };
+		match action {
+			DeleteAction::Cancel => { return; }
+			DeleteAction::Retarget(target) => {
+				for (table, curve, keyframe) in references.iter() {
+					self.tables[*table].0.curves[*curve].keyframes[*keyframe].segment = CurveSegment::Subcurve { curve: target.clone() };
+					self.tables[*table].1.dirty = true;
+				}
+			}
+			DeleteAction::Bake => {
+				let mut baked: Vec<(usize, usize, Vec<CurveKeyframe>)> = Vec::new();
+				{
+					let available_curves = get_available_curves(&self.tables);
+					for (table, curve, _) in references.iter() {
+						if baked.iter().any(|(other_table, other_curve, _)| other_table == table && other_curve == curve) {
+							continue;
+						}
+						baked.push((*table, *curve, bake_subcurve(&self.tables[*table].0.curves[*curve], &name, &available_curves)));
+					}
+				}
+				for (table, curve, keyframes) in baked {
+					self.tables[table].0.curves[curve].keyframes = keyframes;
+					self.tables[table].1.dirty = true;
+					curve_cleared(&mut self.selected_keyframes, (table, curve));
+				}
+			}
+		}
+
+		self.tables[table_num].0.curves.remove(curve_num);
+		self.tables[table_num].1.dirty = true;
+		self.curves_to_show = self.curves_to_show.iter().filter(|(table, curve)| *table != table_num || *curve != curve_num).map(|(table, curve)| (*table, if *table == table_num && *curve > curve_num { *curve - 1 } else { *curve })).collect();
+		curve_removed(&mut self.selected_keyframes, (table_num, curve_num));
 	}
 
 	pub(crate) fn current_keyframe(&mut self, ui: &mut Ui, ctx: &egui::Context) {
@@ -172,10 +343,26 @@ impl CurvEdit {
 		let was_editing_y = ctx.memory(|mem| mem.data.get_temp::<String>(id_y));
 		let was_editing_deg = ctx.memory(|mem| mem.data.get_temp::<String>(id_deg));
 		
-		if let Some((table, curve, keyframe)) = self.selected_keyframe {
+		if self.selected_keyframes.iter().any(|(table, _, _)| self.tables[*table].1.read_only) {
+			ui.add_space(6f32);
+			ui.label("🔒 Reference table, read-only.");
+			ui.set_enabled(false);
+		}
+
+		if self.selected_keyframes.len() > 1 {
+			ui.add_space(6f32);
+			self.group_transform(ui, ctx);
+			ctx.memory_mut(|mem| mem.data.remove_temp::<String>(id_x));
+			ctx.memory_mut(|mem| mem.data.remove_temp::<String>(id_y));
+			ctx.memory_mut(|mem| mem.data.remove_temp::<String>(id_deg));
+		}
+		else if let Some((table, curve, keyframe)) = self.selected_keyframes.last().copied() {
 			ui.add_space(6f32);
 			let list_of_curves = self.tables.iter().flat_map(|(table, _)| table.curves.iter().map(|curve| curve.name.clone()))
 				.chain(BUILTIN_CURVES.iter().map(|curve| curve.name.clone())).collect::<Vec<String>>();
+			let dependencies = DependencyGraph::new(&get_available_curves(&self.tables));
+			let curve_name = &self.tables[table].0.curves[curve].name;
+			let cyclic_choices = list_of_curves.iter().filter(|other| dependencies.would_create_cycle(curve_name, other)).cloned().collect::<Vec<String>>();
 			
 			let (table, file_data) = &mut self.tables[table];
 			let curve = &mut table.curves[curve];
@@ -234,7 +421,7 @@ impl CurvEdit {
 						ui.selectable_value(&mut keyframe.segment, CurveSegment::Linear, "Linear");
 						ui.selectable_value(&mut keyframe.segment, CurveSegment::Polynomial { degree: 2f32, ease_in: None }, "Polynomial");
 						ui.selectable_value(&mut keyframe.segment, CurveSegment::Circular { ease_in: None }, "Circular");
-						if let Some(first) = list_of_curves.iter().find(|string| **string != curve.name) {
+						if let Some(first) = list_of_curves.iter().find(|string| **string != curve.name && !cyclic_choices.contains(string)) {
 							ui.selectable_value(&mut keyframe.segment, CurveSegment::Subcurve { curve: first.clone() }, "Subcurve");
 						} 
 					}).response.clicked() {
@@ -283,7 +470,8 @@ impl CurvEdit {
 							.selected_text(subcurve.as_str())
 							.show_ui(ui, |ui| {
 								for other_curve in list_of_curves.iter().filter(|string| **string != curve.name) {
-									ui.selectable_value(subcurve, other_curve.clone(), other_curve);
+									ui.add_enabled_ui(!cyclic_choices.contains(other_curve), |ui| ui.selectable_value(subcurve, other_curve.clone(), other_curve))
+										.inner.on_disabled_hover_text(format!("{} uses {}, this would create a cycle.", other_curve, curve.name));
 								}
 							}).response.clicked() {
 							file_data.dirty = true;
@@ -302,11 +490,250 @@ impl CurvEdit {
 			ctx.memory_mut(|mem| mem.data.remove_temp::<String>(id_deg));
 		}
 	}
+
+	fn group_transform(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+		ui.label(format!("{} keyframes selected", self.selected_keyframes.len()));
+		ui.add_space(4f32);
+
+		let offset_x = ui.horizontal(|ui| {
+			ui.label("Offset X: ");
+			group_field(ui, ctx, "group_offset_x", "0")
+		}).inner;
+		let offset_y = ui.horizontal(|ui| {
+			ui.label("Offset Y: ");
+			group_field(ui, ctx, "group_offset_y", "0")
+		}).inner;
+		if ui.button("Apply offset").clicked() {
+			if let (Ok(offset_x), Ok(offset_y)) = (f32::from_str(offset_x.as_str()), f32::from_str(offset_y.as_str())) {
+				self.transform_selected_keyframes(|(x, y)| (x + offset_x, y + offset_y));
+			}
+		}
+		ui.separator();
+
+		let scale_x = ui.horizontal(|ui| {
+			ui.label("Scale X: ");
+			group_field(ui, ctx, "group_scale_x", "1")
+		}).inner;
+		let scale_y = ui.horizontal(|ui| {
+			ui.label("Scale Y: ");
+			group_field(ui, ctx, "group_scale_y", "1")
+		}).inner;
+		let id_pivot = Id::new("group_pivot");
+		let mut pivot = ctx.memory(|mem| mem.data.get_temp::<GroupPivot>(id_pivot)).unwrap_or_default();
+		ui.horizontal(|ui| {
+			ui.label("Pivot: ");
+			egui::ComboBox::from_id_source("group_pivot")
+				.selected_text(
+					match pivot {
+						GroupPivot::Center => { "Center of selection" }
+						GroupPivot::First => { "First keyframe" }
+						GroupPivot::Last => { "Last keyframe" }
+						GroupPivot::Origin => { "Origin" }
+					})
+				.show_ui(ui, |ui| {
+					ui.selectable_value(&mut pivot, GroupPivot::Center, "Center of selection");
+					ui.selectable_value(&mut pivot, GroupPivot::First, "First keyframe");
+					ui.selectable_value(&mut pivot, GroupPivot::Last, "Last keyframe");
+					ui.selectable_value(&mut pivot, GroupPivot::Origin, "Origin");
+				});
+		});
+		ctx.memory_mut(|mem| mem.data.insert_temp::<GroupPivot>(id_pivot, pivot));
+		if ui.button("Apply scale").clicked() {
+			if let (Ok(scale_x), Ok(scale_y)) = (f32::from_str(scale_x.as_str()), f32::from_str(scale_y.as_str())) {
+				let positions = self.selected_keyframes.iter().map(|(table, curve, keyframe)| self.tables[*table].0.curves[*curve].keyframes[*keyframe].pos).collect::<Vec<(f32, f32)>>();
+				let (pivot_x, pivot_y) = match pivot {
+					GroupPivot::Center => {
+						let (min_x, max_x) = positions.iter().fold((f32::INFINITY, -f32::INFINITY), |(min, max), (x, _)| (min.min(*x), max.max(*x)));
+						let (min_y, max_y) = positions.iter().fold((f32::INFINITY, -f32::INFINITY), |(min, max), (_, y)| (min.min(*y), max.max(*y)));
+						((min_x + max_x) / 2f32, (min_y + max_y) / 2f32)
+					}
+					GroupPivot::First => { positions.iter().copied().min_by(|a, b| a.0.total_cmp(&b.0)).unwrap_or_default() }
+					GroupPivot::Last => { positions.iter().copied().max_by(|a, b| a.0.total_cmp(&b.0)).unwrap_or_default() }
+					GroupPivot::Origin => { (0f32, 0f32) }
+				};
+				self.transform_selected_keyframes(|(x, y)| (pivot_x + (x - pivot_x) * scale_x, pivot_y + (y - pivot_y) * scale_y));
+			}
+		}
+		ui.separator();
+
+		ui.horizontal(|ui| {
+			ui.label("Set interpolation type: ");
+			let mut segment: Option<CurveSegment> = None;
+			//Only subcurves that none of the selected curves are used by can be chosen
+			let dependencies = DependencyGraph::new(&get_available_curves(&self.tables));
+			let selected_names = self.selected_keyframes.iter().map(|(table, curve, _)| self.tables[*table].0.curves[*curve].name.as_str()).collect::<HashSet<&str>>();
+			let mut subcurves = self.tables.iter().flat_map(|(table, _)| table.curves.iter().map(|curve| curve.name.clone()))
+				.chain(BUILTIN_CURVES.iter().map(|curve| curve.name.clone()))
+				.filter(|other| !selected_names.iter().any(|name| dependencies.would_create_cycle(name, other))).collect::<Vec<String>>();
+			subcurves.sort();
+			subcurves.dedup();
+			egui::ComboBox::from_id_source("group_interptype")
+				.selected_text("")
+				.show_ui(ui, |ui| {
+					ui.selectable_value(&mut segment, Some(CurveSegment::Constant), "Constant");
+					ui.selectable_value(&mut segment, Some(CurveSegment::Linear), "Linear");
+					ui.selectable_value(&mut segment, Some(CurveSegment::Polynomial { degree: 2f32, ease_in: None }), "Polynomial");
+					ui.selectable_value(&mut segment, Some(CurveSegment::Circular { ease_in: None }), "Circular");
+					if !subcurves.is_empty() {
+						ui.separator();
+					}
+					for subcurve in subcurves {
+						let label = format!("Subcurve: {}", subcurve);
+						ui.selectable_value(&mut segment, Some(CurveSegment::Subcurve { curve: subcurve }), label);
+					}
+				});
+			if let Some(segment) = segment {
+				for (table, curve, keyframe) in self.selected_keyframes.iter() {
+					self.tables[*table].0.curves[*curve].keyframes[*keyframe].segment = segment.clone();
+					self.tables[*table].1.dirty = true;
+				}
+			}
+		});
+	}
+
+	fn transform_selected_keyframes(&mut self, transform: impl Fn((f32, f32)) -> (f32, f32)) {
+		let mut transformed: Vec<(usize, usize, Vec<CurveKeyframe>)> = Vec::new();
+		for (table, curve, _) in self.selected_keyframes.iter() {
+			if transformed.iter().any(|(other_table, other_curve, _)| other_table == table && other_curve == curve) {
+				continue;
+			}
+			let mut keyframes = self.tables[*table].0.curves[*curve].keyframes.clone();
+			for keyframe in selected_in_curve(&self.selected_keyframes, (*table, *curve)) {
+				keyframes[keyframe].pos = transform(keyframes[keyframe].pos);
+			}
+			if keyframes.windows(2).any(|pair| pair[1].pos.0 - pair[0].pos.0 < KEYFRAME_MIN_X_DISTANCE) {
+				self.notes.push((Note {
+					text: format!("Cannot transform keyframes: Keyframes of {} would overlap or change order!", self.tables[*table].0.curves[*curve].name),
+					severity: NoteSeverity::Error,
+					timeout: 5f32
+				}, None));
+				return;
+			}
+			transformed.push((*table, *curve, keyframes));
+		}
+
+		for (table, curve, keyframes) in transformed {
+			self.tables[table].0.curves[curve].keyframes = keyframes;
+			self.tables[table].1.dirty = true;
+		}
+	}
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum GroupPivot {
+	#[default]
+	Center,
+	First,
+	Last,
+	Origin
+}
+
+fn group_field(ui: &mut Ui, ctx: &egui::Context, id: &str, default: &str) -> String {
+	let id = Id::new(id);
+	let mut value = ctx.memory(|mem| mem.data.get_temp::<String>(id)).unwrap_or(default.to_string());
+	ui.text_edit_singleline(&mut value);
+	ctx.memory_mut(|mem| mem.data.insert_temp::<String>(id, value.clone()));
+	value
+}
+
+pub(crate) struct DeleteCurveDialog {
+	table: usize,
+	curve: usize,
+	name: String,
+	retarget: Option<String>
+}
+
+enum DeleteAction {
+	Cancel,
+	Retarget(String),
+	Bake
+}
+
+//Replaces all segments using the subcurve with linear segments sampled from the curve as it is now
+fn bake_subcurve(curve: &Curve, subcurve: &str, available_curves: &Vec<&Curve>) -> Vec<CurveKeyframe> {
+	let mut keyframes: Vec<CurveKeyframe> = Vec::new();
+	for (kf_num, keyframe) in curve.keyframes.iter().enumerate() {
+		if !matches!(&keyframe.segment, CurveSegment::Subcurve { curve } if curve == subcurve) {
+			keyframes.push(keyframe.clone());
+			continue;
+		}
+
+		keyframes.push(CurveKeyframe::new(keyframe.pos, CurveSegment::Linear));
+		if let Some(next) = curve.keyframes.get(kf_num + 1) {
+			let width = next.pos.0 - keyframe.pos.0;
+			let samples = BAKE_SAMPLES.min((width / KEYFRAME_MIN_X_DISTANCE) as usize / 2);
+			for sample in 1..samples {
+				let x = keyframe.pos.0 + width * sample as f32 / samples as f32;
+				keyframes.push(CurveKeyframe::new((x, curve.calculate(x, available_curves)), CurveSegment::Linear));
+			}
+		}
+	}
+	keyframes
+}
+
+fn renaming_creates_cycle(tables: &Vec<(CurveTable, TableData)>, (table_num, curve_num): (usize, usize), new_name: &str) -> bool {
+	//Dangling subcurve references to the new name would start pointing at the renamed curve
+	let available_curves = get_available_curves(tables);
+	let curve = &tables[table_num].0.curves[curve_num];
+	if is_effective(tables, (table_num, curve_num)) {
+		let mut dependencies = DependencyGraph::new(&available_curves);
+		dependencies.rename(&curve.name, new_name);
+		dependencies.is_on_cycle(new_name)
+	}
+	else {
+		//Only the shadowed definition takes the new name, so it is added as a curve of its own
+		let renamed = Curve::new(new_name.to_string(), curve.keyframes.clone());
+		let curves = available_curves.iter().copied().chain(std::iter::once(&renamed)).collect::<Vec<&Curve>>();
+		DependencyGraph::new(&curves).is_on_cycle(new_name)
+	}
+}
+
+//The subcurve references that deleting the curve would leave without a curve to use, in the tables we can edit.
+//Deleting a shadowed definition or an override changes nothing for them, as the name still resolves to another definition.
+fn dangling_references(tables: &[(CurveTable, TableData)], (table_num, curve_num): (usize, usize)) -> Vec<(usize, usize, usize)> {
+	let deleted_name = tables[table_num].0.curves[curve_num].name.as_str();
+	let defined_elsewhere = BUILTIN_CURVES.iter().any(|curve| curve.name == deleted_name)
+		|| tables.iter().enumerate().any(|(other_table, (table, _))| table.curves.iter().enumerate().any(|(other_curve, curve)| curve.name == deleted_name && (other_table, other_curve) != (table_num, curve_num)));
+	if defined_elsewhere {
+		return Vec::new();
+	}
+	tables.iter().enumerate().filter(|(_, (_, file_data))| !file_data.read_only).flat_map(|(table_num, (table, _))| table.curves.iter().enumerate().flat_map(move |(curve_num, curve)| {
+		curve.keyframes.iter().enumerate().filter(move |(_, keyframe)| matches!(&keyframe.segment, CurveSegment::Subcurve { curve } if curve == deleted_name)).map(move |(kf_num, _)| (table_num, curve_num, kf_num))
+	})).collect()
+}
+
+//Whether lookups of the curve's name resolve to this definition
+fn is_effective(tables: &Vec<(CurveTable, TableData)>, (table_num, curve_num): (usize, usize)) -> bool {
+	let curve = &tables[table_num].0.curves[curve_num];
+	get_available_curves(tables).iter().any(|other| std::ptr::eq(*other, curve))
+}
+
+//For every curve that a later definition overrides, the name of the file doing so
+fn shadowing_tables(tables: &Vec<(CurveTable, TableData)>) -> HashMap<(usize, usize), String> {
+	let available_curves = get_available_curves(tables);
+	let mut shadowed_by: HashMap<(usize, usize), String> = HashMap::new();
+	for (table_num, (table, _)) in tables.iter().enumerate() {
+		for (curve_num, curve) in table.curves.iter().enumerate() {
+			let Some(effective) = available_curves.iter().find(|other| other.name == curve.name) else { continue; };
+			if std::ptr::eq(*effective, curve) {
+				continue;
+			}
+			let source = tables.iter().find(|(other_table, _)| other_table.curves.iter().any(|other| std::ptr::eq(*effective, other)))
+				.map_or("the builtin curves".to_string(), |(_, file_data)| file_data.file.file_name().unwrap_or("".as_ref()).to_string_lossy().to_string());
+			shadowed_by.insert((table_num, curve_num), source);
+		}
+	}
+	shadowed_by
 }
 
 fn table_entry(ui: &mut Ui, table: &CurveTable, file_data: &mut TableData, notes: &mut Vec<(Note, Option<Instant>)>, button_style: &Arc<Style>) -> bool {
 	let filename = file_data.file.file_name().map_or("".to_string(), |filename| filename.to_string_lossy().to_string());
-	ui.label(&filename);
+	if file_data.dirty {
+		ui.label(format!("{}*", filename)).on_hover_text("This table has unsaved changes.");
+	}
+	else {
+		ui.label(&filename);
+	}
 
 	ui.with_layout(Layout::right_to_left(Align::Center), |ui| -> bool {
 		ui.scope(|ui| {
@@ -325,58 +752,68 @@ fn table_entry(ui: &mut Ui, table: &CurveTable, file_data: &mut TableData, notes
 			} else {
 				false
 			};
-			if ui.button("🖫").on_hover_text("Save table file.").clicked() {
-				let table_content = table.spew();
-
-				match fs::write(&file_data.file, table_content) {
-					Ok(_) => {
-						file_data.dirty = false;
-					}
-					Err(error) => {
-						notes.push((Note {
-							text: format!("Cannot save table {}: {}!", filename, error),
-							severity: NoteSeverity::Error,
-							timeout: 5f32
-						}, None));
-					}
-				}
+			if file_data.read_only {
+				ui.label("🔒").on_hover_text("Reference table, read-only.");
+			}
+			else if ui.button("🖫").on_hover_text("Save table file.").clicked() {
+				save_table(table, file_data, notes);
 			}
 			close
 		}).inner
 	}).inner
 }
 
-fn curve_entry(ui: &mut Ui, curve: &Curve, ctx: &egui::Context, mut is_clicked: bool, can_go_down: bool, can_go_up: bool, button_style: &Arc<Style>) -> (bool, bool, bool, bool, Option<String>) {
+//Curves of reference tables can only be looked at
+fn reference_curve_entry(ui: &mut Ui, curve: &Curve, shadowed_by: Option<&str>, mut is_clicked: bool, button_style: &Arc<Style>) -> bool {
+	ui.add_space(20f32);
+	if shadowed_by.is_some() {
+		ui.visuals_mut().override_text_color = Some(ui.visuals().weak_text_color());
+	}
+
+	ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+		ui.scope(|ui| {
+			ui.set_style(button_style.clone());
+			ui.toggle_value(&mut is_clicked, "👁").on_hover_text("Show curve.");
+		});
+		let label = ui.label(&curve.name);
+		match shadowed_by {
+			Some(shadowed_by) => { label.on_hover_text(format!("Overridden by the definition in {}, FSO will not use this curve.", shadowed_by)); }
+			None => { label.on_hover_text("Reference curve, read-only."); }
+		}
+		is_clicked
+	}).inner
+}
+
+fn curve_entry(ui: &mut Ui, curve: &Curve, shadowed_by: Option<&str>, mut is_clicked: bool, can_go_down: bool, can_go_up: bool, button_style: &Arc<Style>) -> (bool, bool, bool, bool, Option<String>) {
 	//(display, remove, up, down)
 	ui.add_space(20f32);
+	let ctx = ui.ctx().clone();
+	if shadowed_by.is_some() {
+		ui.visuals_mut().override_text_color = Some(ui.visuals().weak_text_color());
+	}
 
 	ui.with_layout(Layout::right_to_left(Align::Center), |ui| -> (bool, bool, bool, bool, Option<String>) {
 
 		let (remove, up, down) = ui.scope(|ui| {
 			ui.set_style(button_style.clone());
-			let remove = if ui.button("🗑").on_hover_text("Delete curve.").clicked() {
-				MessageDialog::new()
-					.set_title("Delete curve?")
-					.set_type(MessageType::Warning)
-					.set_text(&format!("Are you sure you want to delete the curve {}?", curve.name))
-					.show_confirm()
-					.unwrap_or(false)
-			} else {
-				false
-			};
+			let remove = ui.button("🗑").on_hover_text("Delete curve.").clicked();
 			let up = ui.add_enabled(can_go_up, egui::Button::new("🡑")).on_hover_text("Move curve up.").clicked();
 			let down = ui.add_enabled(can_go_down, egui::Button::new("🡓")).on_hover_text("Move curve down.").clicked();
 			ui.toggle_value(&mut is_clicked, "👁").on_hover_text("Show curve.");
 			(remove, up, down)
 		}).inner;
 
-		let id = Id::new(format!("name_{}", curve.name));
+		let id = ui.id().with(("name", &curve.name));
 		let was_editing = ctx.memory(|mem| mem.data.get_temp::<String>(id));
 		let was_typing = was_editing.is_some();
 		let mut new_name_return: Option<String> = None;
 
 		let mut new_name = was_editing.unwrap_or(curve.name.clone());
-		if ui.text_edit_singleline(&mut new_name).lost_focus() {
+		let mut name_edit = ui.text_edit_singleline(&mut new_name);
+		if let Some(shadowed_by) = shadowed_by {
+			name_edit = name_edit.on_hover_text(format!("Overridden by the definition in {}, FSO will not use this curve.", shadowed_by));
+		}
+		if name_edit.lost_focus() {
 			new_name_return = Some(new_name);
 			ctx.memory_mut(|mem| mem.data.remove_temp::<String>(id));
 		}
@@ -82,7 +82,7 @@ impl CurvEdit {
 			ui.horizontal(|ui| {
 				ui.hyperlink_to("FreeSpace Open Curve Wiki", "https://wiki.hard-light.net/index.php/Curves.tbl");
 				ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
-					ui.label("Hover for controls").on_hover_text("Left click to select or drag a keyframe\nRight click to add a keyframe (affected by snap mode)\nCtrl+Right click to delete a keyframe");
+					ui.label("Hover for controls").on_hover_text("Left click to select or drag a keyframe\nShift+Left click to add or remove a keyframe from the selection\nLeft drag on empty space to box select (hold Shift to add)\nRight click to add a keyframe (affected by snap mode)\nCtrl+Right click to delete a keyframe\nArrow keys to nudge the selected keyframes (hold Shift for fine steps)\nTab and Shift+Tab to step through keyframes, Ctrl+Tab through curves\nDelete to delete the selected keyframes, Insert to add a keyframe at the pointer or after the selection\nMouse wheel to zoom, middle drag to pan\nShift+Middle drag to zoom to a box, Fit to keyframes or Fit to range to reset the view\nCtrl+Z to undo, Ctrl+Shift+Z to redo\nCtrl+S to save the current table, Ctrl+Shift+S to save it under a new name\nCtrl+C to copy the selected keyframes or shown curves as table text\nCtrl+V to paste into the selected curve, or as new curves if none is selected\nCtrl+Shift+V to always paste as new curves");
 				});
 			});
 			ui.add_space(2f32);
@@ -1,146 +1,515 @@
 use eframe::emath::Vec2;
 use eframe::epaint::Color32;
-use egui::Id;
-use egui_plot::{Line, MarkerShape, PlotPoints, PlotUi, Points};
+use egui::{Align2, PointerButton};
+use egui_plot::{HLine, Line, LineStyle, MarkerShape, PlotBounds, PlotPoint, PlotPoints, PlotUi, Points, Polygon, Text, VLine};
 use fso_tables_impl::curves::{BUILTIN_CURVES, Curve, CurveKeyframe, CurveSegment, CurveTable};
 use crate::{CurvEditInput, TableData};
-use crate::curves_panel::{CURVE_RENDER_ACCURACY, SnapMode};
+use crate::curves_panel::{Extrapolation, SnapMode, Snapping};
+use crate::sample_cache::SampleCache;
+use crate::selection::{keyframe_inserted, keyframe_removed, make_primary, select_only, selected_in_curve, toggle_selected};
 
 pub(crate) const KEYFRAME_MIN_X_DISTANCE: f32 = 0.001;
 
+const KEYFRAME_COLOR: Color32 = Color32::from_rgb(102, 153, 255);
+const PREVIEW_COLOR: Color32 = Color32::from_rgb(237, 165, 159);
+const SELECTION_COLOR: Color32 = Color32::from_rgb(255, 255, 255);
+const BOX_ZOOM_COLOR: Color32 = Color32::from_rgb(200, 200, 200);
+const EXTRAPOLATION_COLOR: Color32 = Color32::from_rgb(160, 160, 160);
+const BOUNDS_COLOR: Color32 = Color32::from_rgb(110, 110, 110);
+const GUIDE_COLOR: Color32 = Color32::from_rgb(255, 220, 120);
+const READOUT_COLOR: Color32 = Color32::from_rgb(230, 230, 230);
+const EVALUATE_COLOR: Color32 = Color32::from_rgb(120, 220, 160);
+//In pixels
+const KEYFRAME_SNAP_DISTANCE: f32 = 8f32;
+const FIT_MARGIN: f32 = 0.05;
+//Relative to the height of the curve
+const SAMPLE_TOLERANCE: f64 = 0.001;
+const SEGMENT_SAMPLES: usize = 8;
+//Subcurves can have any shape, so they start out with more samples
+const SUBCURVE_SAMPLES: usize = 32;
+const MAX_SUBDIVISIONS: usize = 6;
+const KEYFRAME_INSET: f32 = 0.0001;
+const SCROLL_ZOOM_SPEED: f32 = 0.005;
+const OVERLAY_COLORS: [Color32; 8] = [
+	Color32::from_rgb(102, 153, 255),
+	Color32::from_rgb(255, 170, 64),
+	Color32::from_rgb(110, 210, 120),
+	Color32::from_rgb(230, 100, 200),
+	Color32::from_rgb(240, 220, 80),
+	Color32::from_rgb(80, 215, 220),
+	Color32::from_rgb(180, 130, 255),
+	Color32::from_rgb(200, 200, 200)
+];
+
+//Samples a curve between its first and last keyframe. Linear and constant segments only need their ends,
+//the others start out evenly sampled and are split further until the plotted line is within SAMPLE_TOLERANCE of the curve.
 pub(crate) fn from_curve (
 	curve: &Curve,
-	available_curves: &Vec<&Curve>,
-	points: usize,
-) -> PlotPoints {
-	let (bounds, _) = curve.get_bounds();
-	
-	let increment = (bounds.end - bounds.start) / (points as f32);
-	
-	(0..points + 1)
-		.map(|i| {
-			let x = bounds.start + i as f32 * increment;
-			(x as f64, curve.calculate(x, available_curves) as f64).into()
-		})
-		.collect()
+	available_curves: &Vec<&Curve>
+) -> Vec<[f64; 2]> {
+	let sample = |x: f32| [x as f64, curve.calculate(x, available_curves) as f64];
+	if curve.keyframes.len() < 2 {
+		let (bounds, _) = curve.get_bounds();
+		return (0..SEGMENT_SAMPLES + 1).map(|i| sample(bounds.start + (bounds.end - bounds.start) * i as f32 / SEGMENT_SAMPLES as f32)).collect();
+	}
+
+	let tolerance = sample_tolerance(curve);
+	let mut points: Vec<[f64; 2]> = Vec::new();
+	for pair in curve.keyframes.windows(2) {
+		let (start, end) = (pair[0].pos.0, pair[1].pos.0);
+		//Stopping just short of the next keyframe keeps jumps there vertical
+		let before_end = end - (end - start) * KEYFRAME_INSET;
+		let segment_samples = match &pair[0].segment {
+			CurveSegment::Linear => { 0 }
+			CurveSegment::Constant => { 1 }
+			CurveSegment::Polynomial { .. } | CurveSegment::Circular { .. } => { SEGMENT_SAMPLES }
+			CurveSegment::Subcurve { .. } => { SUBCURVE_SAMPLES }
+		};
+
+		let mut last = sample(start);
+		points.push(last);
+		for i in 1..segment_samples + 1 {
+			let next = sample(if i == segment_samples { before_end } else { start + (end - start) * i as f32 / segment_samples as f32 });
+			if segment_samples > 1 {
+				subdivide(&sample, last, next, tolerance, 0, &mut points);
+			}
+			else {
+				points.push(next);
+			}
+			last = next;
+		}
+	}
+	points.push(sample(curve.keyframes[curve.keyframes.len() - 1].pos.0));
+	points
+}
+
+//Samples any input range of a curve, such as the ranges outside of its keyframes
+pub(crate) fn sample_range(curve: &Curve, available_curves: &Vec<&Curve>, start: f32, end: f32) -> Vec<[f64; 2]> {
+	if start >= end {
+		return Vec::new();
+	}
+	let sample = |x: f32| [x as f64, curve.calculate(x, available_curves) as f64];
+	let tolerance = sample_tolerance(curve);
+	let mut last = sample(start);
+	let mut points = vec![last];
+	for i in 1..SUBCURVE_SAMPLES + 1 {
+		let next = sample(start + (end - start) * i as f32 / SUBCURVE_SAMPLES as f32);
+		subdivide(&sample, last, next, tolerance, 0, &mut points);
+		last = next;
+	}
+	points
+}
+
+fn sample_tolerance(curve: &Curve) -> f64 {
+	let (_, range_y) = curve.get_bounds();
+	((range_y.end - range_y.start).abs() as f64).max(f64::EPSILON) * SAMPLE_TOLERANCE
+}
+
+//Draws the curves beyond their first and last keyframe, and marks where the keyframes end
+pub(crate) fn plot_outside_keyframes(plot_ui: &mut PlotUi, tables: &[(CurveTable, TableData)], curve_numbers: &[(usize, usize)], extrapolation: &Extrapolation, samples: &mut SampleCache) {
+	let available_curves = effective_curves(tables.iter().flat_map(|(table, _)| table.curves.iter()));
+	let overlay = curve_numbers.len() > 1;
+	for (plot_num, (table, curve)) in curve_numbers.iter().enumerate() {
+		let curve = &tables[*table].0.curves[*curve];
+		let Some(domain) = extrapolation.domain(&tables[*table].1.file, curve) else { continue; };
+		let color = if overlay { OVERLAY_COLORS[plot_num % OVERLAY_COLORS.len()].gamma_multiply(0.6) } else { EXTRAPOLATION_COLOR };
+		let (before, after) = samples.outside_points(&tables[*table].1.file, curve, &available_curves, domain);
+		for points in [before, after].into_iter().filter(|points| !points.is_empty()) {
+			plot_ui.line(Line::new(PlotPoints::new(points)).color(color).style(LineStyle::dashed_loose()));
+		}
+		for keyframe in [&curve.keyframes[0], &curve.keyframes[curve.keyframes.len() - 1]] {
+			plot_ui.vline(VLine::new(keyframe.pos.0 as f64).color(BOUNDS_COLOR).width(1f32));
+		}
+	}
+}
+
+//Marks the output of each curve at the input under the pointer, in whichever linked plot it is
+pub(crate) fn plot_hover_readout(plot_ui: &mut PlotUi, tables: &[(CurveTable, TableData)], curve_numbers: &[(usize, usize)], x: f32) {
+	plot_readout(plot_ui, tables, curve_numbers, x, READOUT_COLOR);
+}
+
+//Marks the output of each curve at the input entered to evaluate them at
+pub(crate) fn plot_evaluate_at(plot_ui: &mut PlotUi, tables: &[(CurveTable, TableData)], curve_numbers: &[(usize, usize)], x: f32) {
+	plot_ui.vline(VLine::new(x as f64).color(EVALUATE_COLOR).width(1f32).style(LineStyle::dashed_dense()));
+	plot_readout(plot_ui, tables, curve_numbers, x, EVALUATE_COLOR);
+}
+
+fn plot_readout(plot_ui: &mut PlotUi, tables: &[(CurveTable, TableData)], curve_numbers: &[(usize, usize)], x: f32, color: Color32) {
+	let available_curves = effective_curves(tables.iter().flat_map(|(table, _)| table.curves.iter()));
+	for (table, curve) in curve_numbers.iter() {
+		let y = tables[*table].0.curves[*curve].calculate(x, &available_curves);
+		plot_ui.points(Points::new(PlotPoints::new(vec![[x as f64, y as f64]])).shape(MarkerShape::Circle).radius(3f32).color(color));
+		plot_ui.text(Text::new(PlotPoint::new(x, y), format!(" {}", y)).anchor(Align2::LEFT_BOTTOM).color(color));
+	}
+}
+
+//Adds the points after start up to and including end
+fn subdivide(sample: &impl Fn(f32) -> [f64; 2], start: [f64; 2], end: [f64; 2], tolerance: f64, depth: usize, points: &mut Vec<[f64; 2]>) {
+	let middle = sample(((start[0] + end[0]) / 2.0) as f32);
+	if depth >= MAX_SUBDIVISIONS || (middle[1] - (start[1] + end[1]) / 2.0).abs() <= tolerance {
+		points.push(middle);
+		points.push(end);
+	}
+	else {
+		subdivide(sample, start, middle, tolerance, depth + 1, points);
+		subdivide(sample, middle, end, tolerance, depth + 1, points);
+	}
 }
 
 pub(crate) fn get_available_curves(tables: &Vec<(CurveTable, TableData)>) -> Vec<&Curve> {
+	effective_curves(tables.iter().flat_map(|(table, _)| table.curves.iter()))
+}
+
+//The effective definition of every curve name. Tables are loaded in order, so like in FSO, later definitions override earlier ones.
+pub(crate) fn effective_curves<'a>(table_curves: impl Iterator<Item = &'a Curve>) -> Vec<&'a Curve> {
 	let mut available_curves: Vec<&Curve> = BUILTIN_CURVES.iter().collect::<Vec<&Curve>>();
-	for (table, _) in tables.iter() {
-		available_curves.extend(table.curves.iter());
+	for curve in table_curves {
+		match available_curves.iter().position(|other| other.name == curve.name) {
+			//Builtin curves can't be overridden
+			Some(idx) if idx < BUILTIN_CURVES.len() => {}
+			Some(idx) => { available_curves[idx] = curve; }
+			None => { available_curves.push(curve); }
+		}
 	}
 	available_curves
 }
 
-pub(crate) fn plot_curve (plot_ui: &mut PlotUi, ctx: &egui::Context, input: &CurvEditInput, tables: &mut Vec<(CurveTable, TableData)>, curve_number: &(usize, usize), drag_mode: &SnapMode, is_dragging: &mut bool, selected_keyframe: &mut Option<(usize, usize, usize)>) {
-	let available_curves = get_available_curves(tables);
-	
-	let curve = &tables[curve_number.0].0.curves[curve_number.1];
-	let curve_points = from_curve( curve, &available_curves, CURVE_RENDER_ACCURACY);
+//A table can override the curves of tables it loads after, but not builtin curves, its own curves or those of tables loading later
+pub(crate) fn name_taken(tables: &[(CurveTable, TableData)], table_num: usize, name: &str) -> bool {
+	BUILTIN_CURVES.iter().any(|curve| curve.name == name)
+		|| tables.iter().skip(table_num).any(|(table, _)| table.curves.iter().any(|curve| curve.name == name))
+}
+
+pub(crate) fn keyframe_bounds(curves: &[&Curve]) -> PlotBounds {
+	let mut bounds = PlotBounds::NOTHING;
+	for keyframe in curves.iter().flat_map(|curve| curve.keyframes.iter()) {
+		bounds.extend_with(&PlotPoint::new(keyframe.pos.0, keyframe.pos.1));
+	}
+	with_fit_margin(bounds)
+}
+
+pub(crate) fn range_bounds(curves: &[&Curve]) -> PlotBounds {
+	let mut bounds = PlotBounds::NOTHING;
+	for curve in curves.iter() {
+		let (range_x, range_y) = curve.get_bounds();
+		bounds.extend_with(&PlotPoint::new(range_x.start, range_y.start));
+		bounds.extend_with(&PlotPoint::new(range_x.end, range_y.end));
+	}
+	with_fit_margin(bounds)
+}
+
+fn with_fit_margin(mut bounds: PlotBounds) -> PlotBounds {
+	//Flat curves would otherwise end up with an empty range
+	if bounds.width() <= 0.0 {
+		bounds.expand_x(0.5);
+	}
+	if bounds.height() <= 0.0 {
+		bounds.expand_y(0.5);
+	}
+	bounds.add_relative_margin_x(Vec2::splat(FIT_MARGIN));
+	bounds.add_relative_margin_y(Vec2::splat(FIT_MARGIN));
+	bounds
+}
+
+//Wheel to zoom, middle drag to pan, shift + middle drag to zoom to a box.
+//Left and right click are left alone, as they edit keyframes.
+pub(crate) fn navigate_plot(plot_ui: &mut PlotUi, ctx: &egui::Context, fit: Option<PlotBounds>) {
+	if let Some(fit) = fit {
+		plot_ui.set_plot_bounds(fit);
+		return;
+	}
 
-	plot_ui.line(Line::new(curve_points).name(&curve.name));
+	let response = plot_ui.response().clone();
+	let id_box_zoom = response.id.with("BoxZoom");
+	let box_start = ctx.memory(|mem| mem.data.get_temp::<(f64, f64)>(id_box_zoom)).map(|(x, y)| PlotPoint::new(x, y));
+	let shift_held = ctx.input(|i| i.modifiers.shift);
+
+	if response.drag_started_by(PointerButton::Middle) && shift_held {
+		if let Some(start) = plot_ui.pointer_coordinate() {
+			ctx.memory_mut(|mem| mem.data.insert_temp::<(f64, f64)>(id_box_zoom, (start.x, start.y)));
+		}
+	}
+	else if let Some(start) = box_start {
+		if let Some(end) = plot_ui.pointer_coordinate() {
+			if response.dragged_by(PointerButton::Middle) {
+				plot_ui.polygon(Polygon::new(PlotPoints::new(vec![[start.x, start.y], [end.x, start.y], [end.x, end.y], [start.x, end.y]]))
+					.stroke((1f32, BOX_ZOOM_COLOR))
+					.fill_color(BOX_ZOOM_COLOR.gamma_multiply(0.1)));
+			}
+			else {
+				let bounds = PlotBounds::from_min_max([start.x.min(end.x), start.y.min(end.y)], [start.x.max(end.x), start.y.max(end.y)]);
+				if bounds.width() > 0.0 && bounds.height() > 0.0 {
+					plot_ui.set_plot_bounds(bounds);
+				}
+			}
+		}
+		if !response.dragged_by(PointerButton::Middle) {
+			ctx.memory_mut(|mem| mem.data.remove_temp::<(f64, f64)>(id_box_zoom));
+		}
+	}
+	else if response.dragged_by(PointerButton::Middle) {
+		plot_ui.translate_bounds(-response.drag_delta());
+	}
+
+	//The plot's own zooming is off, so pinching and Ctrl+scroll, which egui turns into a zoom delta, are handled here as well
+	if response.hovered() {
+		let (scroll, zoom) = ctx.input(|i| (i.smooth_scroll_delta.y, i.zoom_delta()));
+		if scroll != 0f32 || zoom != 1f32 {
+			plot_ui.zoom_bounds_around_hovered(Vec2::splat((scroll * SCROLL_ZOOM_SPEED).exp() * zoom));
+		}
+	}
+}
+
+pub(crate) fn plot_curves (plot_ui: &mut PlotUi, ctx: &egui::Context, input: &CurvEditInput, tables: &mut Vec<(CurveTable, TableData)>, curve_numbers: &[(usize, usize)], snapping: &Snapping, samples: &mut SampleCache, is_dragging: &mut bool, selected_keyframes: &mut Vec<(usize, usize, usize)>) {
+	let available_curves = get_available_curves(tables);
+	let overlay = curve_numbers.len() > 1;
 
 	let point_size = Vec2::from(plot_ui.transform().dpos_dvalue().map(|v| (15f32 / v as f32).abs()));
-	let mut point_bounds: Vec<(Vec2, Vec2)> = Vec::new();
-	for (i, keyframe) in curve.keyframes.iter().enumerate() {
-		let kf_point = Points::new(PlotPoints::new(vec![[keyframe.pos.0 as f64, keyframe.pos.1 as f64]]));
-		point_bounds.push((Vec2::from(keyframe.pos) - point_size, Vec2::from(keyframe.pos) + point_size));
-		plot_ui.points(kf_point.name(format!("Keyframe {}", i + 1))
-			.filled(true)
-			.radius(5f32)
-			.shape(MarkerShape::Square)
-			.color(Color32::from_rgb(102, 153, 255)));
-	}
-
-	type DraggingPntTuple = (usize, Vec2);
-	let id_dragging = Id::new(format!("Dragging{}", curve.name));
-	let was_dragging = ctx.memory(|mem| mem.data.get_temp::<DraggingPntTuple>(id_dragging));
+	//(curve in this plot, keyframe, lower bound, upper bound)
+	let mut point_bounds: Vec<(usize, usize, Vec2, Vec2)> = Vec::new();
+	for (plot_num, curve_number) in curve_numbers.iter().enumerate() {
+		let curve = &tables[curve_number.0].0.curves[curve_number.1];
+		let color = overlay.then(|| OVERLAY_COLORS[plot_num % OVERLAY_COLORS.len()]);
+		let curve_points = PlotPoints::new(samples.points(&tables[curve_number.0].1.file, curve, &available_curves));
+
+		let mut line = Line::new(curve_points).name(&curve.name);
+		if let Some(color) = color {
+			line = line.color(color);
+		}
+		plot_ui.line(line);
+
+		for (i, keyframe) in curve.keyframes.iter().enumerate() {
+			let kf_point = Points::new(PlotPoints::new(vec![[keyframe.pos.0 as f64, keyframe.pos.1 as f64]]));
+			point_bounds.push((plot_num, i, Vec2::from(keyframe.pos) - point_size, Vec2::from(keyframe.pos) + point_size));
+			//In overlays, keyframes share the legend entry of their curve
+			plot_ui.points(kf_point.name(if overlay { curve.name.clone() } else { format!("Keyframe {}", i + 1) })
+				.filled(true)
+				.radius(5f32)
+				.shape(MarkerShape::Square)
+				.color(color.unwrap_or(KEYFRAME_COLOR)));
+		}
+
+		let selected = selected_in_curve(selected_keyframes, *curve_number);
+		if !selected.is_empty() {
+			let selected_points = selected.iter().map(|pnt| [curve.keyframes[*pnt].pos.0 as f64, curve.keyframes[*pnt].pos.1 as f64]).collect::<Vec<[f64; 2]>>();
+			plot_ui.points(Points::new(PlotPoints::new(selected_points))
+				.filled(false)
+				.radius(8f32)
+				.shape(MarkerShape::Square)
+				.color(SELECTION_COLOR));
+		}
+	}
+
+	//Later curves are drawn on top, so they are picked first
+	let point_at = |mouse_coords: &Vec2| point_bounds.iter().rev().find(|(_, _, bound_lower, bound_upper)| {
+		bound_lower.x < mouse_coords.x && bound_lower.y < mouse_coords.y && bound_upper.x > mouse_coords.x && bound_upper.y > mouse_coords.y
+	}).map(|(plot_num, pnt, _, _)| (*plot_num, *pnt));
+
+	//How far the selected keyframes have been dragged
+	let id_dragging = plot_ui.response().id.with("Dragging");
+	let was_dragging = ctx.memory(|mem| mem.data.get_temp::<Vec2>(id_dragging));
+	//Where a box selection started, and whether it adds to the selection
+	type SelectingTuple = (f64, f64, bool);
+	let id_selecting = plot_ui.response().id.with("Selecting");
+	let was_selecting = ctx.memory(|mem| mem.data.get_temp::<SelectingTuple>(id_selecting));
+
+	//Only keyframes of the editable curves in this plot are moved along
+	let moving = curve_numbers.iter().map(|(table, curve)| {
+		if tables[*table].1.read_only {
+			return Vec::new();
+		}
+		let num_keyframes = tables[*table].0.curves[*curve].keyframes.len();
+		selected_in_curve(selected_keyframes, (*table, *curve)).into_iter().filter(|pnt| *pnt < num_keyframes).collect()
+	}).collect::<Vec<Vec<usize>>>();
+	//The grabbed keyframe is snapped and the rest of the selection keeps its distance to it, so selected keyframes never snap onto each other
+	let drag_mode = snapping.mode;
+	let grid = snapping.targets.grid_steps(plot_ui.transform());
+	let snap_tolerance = Vec2::from(plot_ui.transform().dvalue_dpos().map(|v| (KEYFRAME_SNAP_DISTANCE * v as f32).abs()));
+	let snap_axes = (*drag_mode != SnapMode::SnapY, matches!(drag_mode, SnapMode::NoSnap | SnapMode::SnapY));
+	let snap_keyframes = if snapping.targets.keyframes {
+		snapping.shown_curves.iter().flat_map(|(table, curve)| {
+			let moving_here = curve_numbers.iter().position(|curve_number| *curve_number == (*table, *curve)).map(|plot_num| moving[plot_num].as_slice()).unwrap_or_default();
+			tables[*table].0.curves[*curve].keyframes.iter().enumerate().filter(move |(pnt, _)| !moving_here.contains(pnt)).map(move |(_, keyframe)| ((*table, *curve), keyframe.pos))
+		}).collect::<Vec<((usize, usize), (f32, f32))>>()
+	}
+	else {
+		Vec::new()
+	};
+	let grabbed = selected_keyframes.last().filter(|(table, curve, pnt)| {
+		curve_numbers.iter().zip(moving.iter()).any(|(curve_number, keyframes)| *curve_number == (*table, *curve) && keyframes.contains(pnt))
+	}).map(|(table, curve, pnt)| Vec2::from(tables[*table].0.curves[*curve].keyframes[*pnt].pos));
+	let snapped_offset = |dragged: &Vec2| -> (Vec2, Option<f32>, Option<f32>) {
+		match grabbed {
+			Some(grabbed) => {
+				let targets_x = snap_keyframes.iter().map(|(_, (x, _))| *x).collect::<Vec<f32>>();
+				let targets_y = snap_keyframes.iter().map(|(_, (_, y))| *y).collect::<Vec<f32>>();
+				let (pos, guide_x, guide_y) = snap_position(grabbed + *dragged, grid, (&targets_x, &targets_y), snap_tolerance, snap_axes);
+				(pos - grabbed, guide_x, guide_y)
+			}
+			None => (*dragged, None, None)
+		}
+	};
+	let moved_positions = |dragged: &Vec2| -> Vec<Vec<(f32, f32)>> {
+		let (dragged, _, _) = snapped_offset(dragged);
+		let (lower_bound, upper_bound) = curve_numbers.iter().zip(moving.iter()).fold((-f32::INFINITY, f32::INFINITY), |(lower, upper), ((table, curve), keyframes)| {
+			let (curve_lower, curve_upper) = keyframe_offset_bounds(&tables[*table].0.curves[*curve], keyframes);
+			(lower.max(curve_lower), upper.min(curve_upper))
+		});
+		let offset = Vec2::new(dragged.x.max(lower_bound).min(upper_bound), dragged.y);
+		curve_numbers.iter().zip(moving.iter()).map(|((table, curve), keyframes)| {
+			let curve = &tables[*table].0.curves[*curve];
+			//The grabbed keyframe was already snapped, the others keep their distance to it
+			keyframes.iter().map(|pnt| calculate_new_keyframe_pos(*pnt, curve, &offset, &available_curves, drag_mode, None)).collect()
+		}).collect()
+	};
 	
 	if let Some(mouse_coords) = plot_ui.pointer_coordinate() {
 		let mouse_coords: Vec2 = mouse_coords.to_vec2();
 		
 		if input.escape_pressed {
-			ctx.memory_mut(|mem| mem.data.remove_temp::<DraggingPntTuple>(id_dragging));
+			ctx.memory_mut(|mem| mem.data.remove_temp::<Vec2>(id_dragging));
+			ctx.memory_mut(|mem| mem.data.remove_temp::<SelectingTuple>(id_selecting));
 		}
-		else if plot_ui.response().hovered() && input.pointer_down {
-			let pointer_translate = plot_ui.pointer_coordinate_drag_delta();
-
-			let new_drag: Option<DraggingPntTuple> = if let Some((pnt, dragged)) = was_dragging {
-				Some((pnt, dragged + pointer_translate))
+		else if let Some((start_x, start_y, additive)) = was_selecting {
+			let (min_x, max_x) = (start_x.min(mouse_coords.x as f64), start_x.max(mouse_coords.x as f64));
+			let (min_y, max_y) = (start_y.min(mouse_coords.y as f64), start_y.max(mouse_coords.y as f64));
+			if input.pointer_down {
+				plot_ui.polygon(Polygon::new(PlotPoints::new(vec![[min_x, min_y], [max_x, min_y], [max_x, max_y], [min_x, max_y]]))
+					.stroke((1f32, SELECTION_COLOR))
+					.fill_color(SELECTION_COLOR.gamma_multiply(0.1)));
 			}
 			else {
-				point_bounds.iter().enumerate().find(|(_, (bound_lower, bound_upper))| {
-					bound_lower.x < mouse_coords.x && bound_lower.y < mouse_coords.y && bound_upper.x > mouse_coords.x && bound_upper.y > mouse_coords.y
-				}).map( |(i, _)| {
-					(i, pointer_translate)
-				})
-			};
-
-			if let Some(new_drag) = new_drag {
-				*selected_keyframe = Some((curve_number.0, curve_number.1, new_drag.0));
-				
-				let new_pos = calculate_new_keyframe_pos(new_drag.0, curve, &new_drag.1, &available_curves, drag_mode);
-				let mut curve_preview = curve.clone();
-				curve_preview.keyframes[new_drag.0].pos = new_pos;
-				
+				ctx.memory_mut(|mem| mem.data.remove_temp::<SelectingTuple>(id_selecting));
+				if !additive {
+					selected_keyframes.clear();
+				}
+				for curve_number in curve_numbers.iter() {
+					for (pnt, keyframe) in tables[curve_number.0].0.curves[curve_number.1].keyframes.iter().enumerate() {
+						let (x, y) = (keyframe.pos.0 as f64, keyframe.pos.1 as f64);
+						if x >= min_x && x <= max_x && y >= min_y && y <= max_y {
+							make_primary(selected_keyframes, (curve_number.0, curve_number.1, pnt));
+						}
+					}
+				}
+			}
+		}
+		else if let Some(dragged) = was_dragging {
+			if input.pointer_down {
+				let dragged = dragged + plot_ui.pointer_coordinate_drag_delta();
 				*is_dragging = true;
-				ctx.memory_mut(|mem| mem.data.insert_temp::<DraggingPntTuple>(id_dragging, new_drag));
+				ctx.memory_mut(|mem| mem.data.insert_temp::<Vec2>(id_dragging, dragged));
+
+				let (_, guide_x, guide_y) = snapped_offset(&dragged);
+				if let Some(x) = guide_x {
+					plot_ui.vline(VLine::new(x as f64).color(GUIDE_COLOR).style(LineStyle::dashed_dense()));
+				}
+				if let Some(y) = guide_y {
+					plot_ui.hline(HLine::new(y as f64).color(GUIDE_COLOR).style(LineStyle::dashed_dense()));
+				}
 
-				let curve_points_preview = from_curve( &curve_preview, &available_curves, CURVE_RENDER_ACCURACY);
-				plot_ui.line(Line::new(curve_points_preview).color(Color32::from_rgb(237, 165, 159)).name(format!("Preview: {}", &curve.name)));
+				for (((table, curve), keyframes), new_positions) in curve_numbers.iter().zip(moving.iter()).zip(moved_positions(&dragged)) {
+					if keyframes.is_empty() {
+						continue;
+					}
+					let curve = &tables[*table].0.curves[*curve];
+					let mut curve_preview = curve.clone();
+					for (pnt, new_pos) in keyframes.iter().zip(new_positions) {
+						curve_preview.keyframes[*pnt].pos = new_pos;
+					}
+					let curve_points_preview = PlotPoints::new(from_curve( &curve_preview, &available_curves));
+					plot_ui.line(Line::new(curve_points_preview).color(PREVIEW_COLOR).name(format!("Preview: {}", &curve.name)));
+				}
 			}
-		}
-		else if let Some((pnt, dragged)) = was_dragging {
-			let new_pos = calculate_new_keyframe_pos(pnt, curve, &dragged, &available_curves, drag_mode);
-			
-			ctx.memory_mut(|mem| mem.data.remove_temp::<DraggingPntTuple>(id_dragging));
+			else {
+				let new_positions = moved_positions(&dragged);
+				ctx.memory_mut(|mem| mem.data.remove_temp::<Vec2>(id_dragging));
 
-			let table = &mut tables[curve_number.0];
-			let curve = &mut table.0.curves[curve_number.1];
-			
-			table.1.dirty = true;
-			curve.keyframes[pnt].pos = new_pos;
+				for ((curve_number, keyframes), new_positions) in curve_numbers.iter().zip(moving.iter()).zip(new_positions) {
+					if keyframes.is_empty() {
+						continue;
+					}
+					let table = &mut tables[curve_number.0];
+					table.1.dirty = true;
+					for (pnt, new_pos) in keyframes.iter().zip(new_positions) {
+						table.0.curves[curve_number.1].keyframes[*pnt].pos = new_pos;
+					}
+				}
+			}
 		}
-		else if input.right_clicked 
+		else if plot_ui.response().hovered() && input.pointer_pressed {
+			if let Some((plot_num, pnt)) = point_at(&mouse_coords) {
+				let keyframe = (curve_numbers[plot_num].0, curve_numbers[plot_num].1, pnt);
+				if input.shift_held {
+					toggle_selected(selected_keyframes, keyframe);
+				}
+				else {
+					//Grabbing an already selected keyframe drags the whole selection along
+					if selected_keyframes.contains(&keyframe) {
+						make_primary(selected_keyframes, keyframe);
+					}
+					else {
+						select_only(selected_keyframes, keyframe);
+					}
+					*is_dragging = true;
+					ctx.memory_mut(|mem| mem.data.insert_temp::<Vec2>(id_dragging, Vec2::ZERO));
+				}
+			}
+			else {
+				ctx.memory_mut(|mem| mem.data.insert_temp::<SelectingTuple>(id_selecting, (mouse_coords.x as f64, mouse_coords.y as f64, input.shift_held)));
+			}
+		}
+		else if (input.right_clicked || input.insert_pressed)
 			&& mouse_coords.x > plot_ui.plot_bounds().min()[0] as f32
 			&& mouse_coords.y > plot_ui.plot_bounds().min()[1] as f32
 			&& mouse_coords.x < plot_ui.plot_bounds().max()[0] as f32
 			&& mouse_coords.y < plot_ui.plot_bounds().max()[1] as f32{
-			let point_clicked =  point_bounds.iter().enumerate().find(|(_, (bound_lower, bound_upper))| {
-				bound_lower.x < mouse_coords.x && bound_lower.y < mouse_coords.y && bound_upper.x > mouse_coords.x && bound_upper.y > mouse_coords.y
-			});
-
-			if let Some((pnt, ..)) = point_clicked {
-				if input.ctrl_held && curve.keyframes.len() > 2 {
+			if let Some((plot_num, pnt)) = point_at(&mouse_coords) {
+				let curve_number = curve_numbers[plot_num];
+				if input.right_clicked && input.ctrl_held && !tables[curve_number.0].1.read_only && tables[curve_number.0].0.curves[curve_number.1].keyframes.len() > 2 {
 					let table = &mut tables[curve_number.0];
 					let curve = &mut table.0.curves[curve_number.1];
 					
 					curve.keyframes.remove(pnt);
 					table.1.dirty = true;
+					keyframe_removed(selected_keyframes, curve_number, pnt);
 				}
 			}
 			else {
-				let point_upper =  curve.keyframes.iter().enumerate().find(|(_, kf)| {
-					kf.pos.0 >= mouse_coords.x
-				}).map(|(pnt, _)| pnt);
+				//New keyframes go to the curve that is being edited, if it's in this plot. Reference curves never get any.
+				let editable = |curve_number: &&(usize, usize)| !tables[curve_number.0].1.read_only;
+				let edited = selected_keyframes.last().and_then(|(table, curve, _)| curve_numbers.iter().find(|curve_number| **curve_number == (*table, *curve)));
+				let Some(&curve_number) = edited.filter(editable).or(curve_numbers.iter().find(editable)) else { return; };
+				let curve = &tables[curve_number.0].0.curves[curve_number.1];
 				
+				//Snapping onto the X of one of the curve's own keyframes would put two keyframes at the same X
+				let targets_x = snap_keyframes.iter().filter(|(other, _)| *other != curve_number).map(|(_, (x, _))| *x).collect::<Vec<f32>>();
+				let targets_y = snap_keyframes.iter().map(|(_, (_, y))| *y).collect::<Vec<f32>>();
+				let (Vec2 { x: new_x, y: new_y }, _, _) = snap_position(mouse_coords, grid, (&targets_x, &targets_y), snap_tolerance, snap_axes);
 				let new_pos = match drag_mode {
 					SnapMode::NoSnap | SnapMode::SnapY => {
 						(
-							mouse_coords.x,
-							mouse_coords.y
+							new_x,
+							new_y
 						)
 					}
 					SnapMode::SnapX | SnapMode::SnapCurve => {
-						let new_y = curve.calculate(mouse_coords.x, &available_curves);
+						let new_y = curve.calculate(new_x, &available_curves);
 						(
-							mouse_coords.x,
+							new_x,
 							new_y
 						)
 					}
 				};
 
+				//Snapping to the grid can land on the X of an existing keyframe
+				if curve.keyframes.iter().any(|keyframe| (keyframe.pos.0 - new_pos.0).abs() < KEYFRAME_MIN_X_DISTANCE) {
+					return;
+				}
+
+				let point_upper =  curve.keyframes.iter().enumerate().find(|(_, kf)| {
+					kf.pos.0 >= new_pos.0
+				}).map(|(pnt, _)| pnt);
+				
 				let table = &mut tables[curve_number.0];
 				let curve = &mut table.0.curves[curve_number.1];
 
@@ -148,6 +517,7 @@ pub(crate) fn plot_curve (plot_ui: &mut PlotUi, ctx: &egui::Context, input: &Cur
 				
 				if let Some(insert) = point_upper {
 					curve.keyframes.insert(insert, CurveKeyframe::new(new_pos, CurveSegment::Constant));
+					keyframe_inserted(selected_keyframes, curve_number, insert);
 				}
 				else {
 					curve.keyframes.push(CurveKeyframe::new(new_pos, CurveSegment::Constant));
@@ -157,38 +527,79 @@ pub(crate) fn plot_curve (plot_ui: &mut PlotUi, ctx: &egui::Context, input: &Cur
 	}
 }
 
-fn calculate_new_keyframe_pos(pnt: usize, curve: &Curve, dragged: &Vec2, available_curves: &Vec<&Curve>, drag_mode: &SnapMode) -> (f32, f32) {
-	let lower_bound = if pnt <= 0 { -f32::INFINITY } else { curve.keyframes[pnt - 1].pos.0 + KEYFRAME_MIN_X_DISTANCE };
-	let upper_bound = if pnt >= curve.keyframes.len() - 1 { f32::INFINITY } else { curve.keyframes[pnt + 1].pos.0 - KEYFRAME_MIN_X_DISTANCE };
+//How far the given keyframes can be moved along X together without passing the keyframes around them
+pub(crate) fn keyframe_offset_bounds(curve: &Curve, keyframes: &[usize]) -> (f32, f32) {
+	let mut lower_bound = -f32::INFINITY;
+	let mut upper_bound = f32::INFINITY;
+	for pnt in keyframes.iter().copied() {
+		let x = curve.keyframes[pnt].pos.0;
+		if pnt > 0 && !keyframes.contains(&(pnt - 1)) {
+			lower_bound = lower_bound.max(curve.keyframes[pnt - 1].pos.0 + KEYFRAME_MIN_X_DISTANCE - x);
+		}
+		if pnt + 1 < curve.keyframes.len() && !keyframes.contains(&(pnt + 1)) {
+			upper_bound = upper_bound.min(curve.keyframes[pnt + 1].pos.0 - KEYFRAME_MIN_X_DISTANCE - x);
+		}
+	}
+	(lower_bound, upper_bound)
+}
+
+pub(crate) fn snap_to_grid(value: f32, step: f32) -> f32 {
+	(value / step).round() * step
+}
+
+//Snaps to keyframe coordinates closer than the tolerance, or else to the grid, each axis on its own.
+//Returns the keyframe coordinates that were snapped to as well, to draw guides at.
+fn snap_position(pos: Vec2, grid: Option<Vec2>, (targets_x, targets_y): (&[f32], &[f32]), tolerance: Vec2, (snap_x, snap_y): (bool, bool)) -> (Vec2, Option<f32>, Option<f32>) {
+	let nearest = |value: f32, coordinates: &mut dyn Iterator<Item = f32>, tolerance: f32| {
+		coordinates.filter(|coordinate| (coordinate - value).abs() <= tolerance).min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()))
+	};
+	let mut snapped = pos;
+	let mut guide_x: Option<f32> = None;
+	let mut guide_y: Option<f32> = None;
+	if snap_x {
+		guide_x = nearest(pos.x, &mut targets_x.iter().copied(), tolerance.x);
+		snapped.x = guide_x.or(grid.map(|grid| snap_to_grid(pos.x, grid.x))).unwrap_or(pos.x);
+	}
+	if snap_y {
+		guide_y = nearest(pos.y, &mut targets_y.iter().copied(), tolerance.y);
+		snapped.y = guide_y.or(grid.map(|grid| snap_to_grid(pos.y, grid.y))).unwrap_or(pos.y);
+	}
+	(snapped, guide_x, guide_y)
+}
 
+//The offset is expected to already be limited by keyframe_offset_bounds.
+//With a grid, the axes that move land on multiples of its increments, and the curve is evaluated at the snapped X.
+pub(crate) fn calculate_new_keyframe_pos(pnt: usize, curve: &Curve, dragged: &Vec2, available_curves: &Vec<&Curve>, drag_mode: &SnapMode, grid: Option<Vec2>) -> (f32, f32) {
 	let kf = &curve.keyframes[pnt];
+	let snap_x = |x: f32| grid.map_or(x, |grid| snap_to_grid(x, grid.x));
+	let snap_y = |y: f32| grid.map_or(y, |grid| snap_to_grid(y, grid.y));
 
 	match drag_mode {
 		SnapMode::NoSnap => {
 			(
-				(kf.pos.0 + dragged.x).clamp(lower_bound, upper_bound),
-				kf.pos.1 + dragged.y
+				snap_x(kf.pos.0 + dragged.x),
+				snap_y(kf.pos.1 + dragged.y)
 			)
 		}
 		SnapMode::SnapX => {
 			(
-				(kf.pos.0 + dragged.x).clamp(lower_bound, upper_bound),
+				snap_x(kf.pos.0 + dragged.x),
 				kf.pos.1
 			)
 		}
 		SnapMode::SnapY => {
 			(
 				kf.pos.0,
-				kf.pos.1 + dragged.y
+				snap_y(kf.pos.1 + dragged.y)
 			)
 		}
 		SnapMode::SnapCurve => {
-			let new_x = (kf.pos.0 + dragged.x).clamp(lower_bound, upper_bound);
-			let new_y = curve.calculate(new_x, &available_curves);
+			let new_x = snap_x(kf.pos.0 + dragged.x);
+			let new_y = curve.calculate(new_x, available_curves);
 			(
 				new_x,
 				new_y
 			)
 		}
 	}
-}
\ No newline at end of file
+}
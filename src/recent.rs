@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+use egui::Ui;
+use crate::CurvEdit;
+
+const RECENT_LENGTH: usize = 10;
+
+#[derive(Clone)]
+pub(crate) struct RecentEntry {
+	pub(crate) path: PathBuf,
+	pub(crate) is_directory: bool,
+	//Pinned entries are kept at the top and survive clearing
+	pub(crate) pinned: bool
+}
+
+enum RecentAction {
+	Open(PathBuf, bool),
+	TogglePin(usize),
+	Clear
+}
+
+impl CurvEdit {
+	pub(crate) fn remember_recent(&mut self, path: &Path, is_directory: bool) {
+		let pinned = match self.recent.iter().position(|entry| entry.path == path && entry.is_directory == is_directory) {
+			Some(idx) => self.recent.remove(idx).pinned,
+			None => false
+		};
+		let first_unpinned = self.recent.iter().position(|entry| !entry.pinned).unwrap_or(self.recent.len());
+		self.recent.insert(if pinned { 0 } else { first_unpinned }, RecentEntry { path: path.to_path_buf(), is_directory, pinned });
+
+		let mut num_unpinned = 0;
+		self.recent.retain(|entry| {
+			num_unpinned += usize::from(!entry.pinned);
+			entry.pinned || num_unpinned <= RECENT_LENGTH
+		});
+	}
+
+	pub(crate) fn recent_menu(&mut self, ui: &mut Ui) {
+		let mut action: Option<RecentAction> = None;
+		ui.add_enabled_ui(!self.recent.is_empty(), |ui| {
+			ui.menu_button("Open Recent", |ui| {
+				for (heading, is_directory) in [("Tables", false), ("Directories", true)] {
+					ui.label(heading);
+					for (idx, entry) in self.recent.iter().enumerate().filter(|(_, entry)| entry.is_directory == is_directory) {
+						ui.horizontal(|ui| {
+							if ui.selectable_label(entry.pinned, "📌").on_hover_text(if entry.pinned { "Unpin." } else { "Pin, so clearing keeps this entry." }).clicked() {
+								action = Some(RecentAction::TogglePin(idx));
+							}
+							let name = entry.path.file_name().unwrap_or(entry.path.as_os_str()).to_string_lossy();
+							if ui.button(name).on_hover_text(entry.path.to_string_lossy()).clicked() {
+								action = Some(RecentAction::Open(entry.path.clone(), entry.is_directory));
+								ui.close_menu();
+							}
+						});
+					}
+					ui.separator();
+				}
+				if ui.button("Clear Recent").on_hover_text("Forget all entries that are not pinned.").clicked() {
+					action = Some(RecentAction::Clear);
+				}
+			});
+		});
+
+		match action {
+			Some(RecentAction::Open(path, false)) => { self.try_open_file(path); }
+			Some(RecentAction::Open(path, true)) => {
+				self.remember_recent(&path, true);
+				self.import_directory(&path, false);
+			}
+			Some(RecentAction::TogglePin(idx)) => {
+				let mut entry = self.recent.remove(idx);
+				entry.pinned = !entry.pinned;
+				let first_unpinned = self.recent.iter().position(|entry| !entry.pinned).unwrap_or(self.recent.len());
+				self.recent.insert(first_unpinned, entry);
+			}
+			Some(RecentAction::Clear) => { self.recent.retain(|entry| entry.pinned); }
+			None => {}
+		}
+	}
+}
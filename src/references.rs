@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::CurvEdit;
+use crate::note_bar::{Note, NoteSeverity};
+
+impl CurvEdit {
+	//Loads all curve tables below a directory as read-only references
+	pub(crate) fn add_reference_directory(&mut self, path: &Path) {
+		self.import_directory(path, true);
+	}
+
+	//Loads the tables of all mods listed as dependencies in a mod.ini as read-only references
+	pub(crate) fn add_mod_ini_references(&mut self, mod_ini: &Path) {
+		let dependencies = match fs::read_to_string(mod_ini) {
+			Ok(content) => mod_ini_dependencies(&content),
+			Err(error) => {
+				self.notes.push((Note {
+					text: format!("Cannot read {}: {}!", mod_ini.to_string_lossy(), error),
+					severity: NoteSeverity::Error,
+					timeout: 5f32
+				}, None));
+				return;
+			}
+		};
+
+		if dependencies.is_empty() {
+			self.notes.push((Note {
+				text: format!("{} does not list any dependencies!", mod_ini.to_string_lossy()),
+				severity: NoteSeverity::Warning,
+				timeout: 5f32
+			}, None));
+			return;
+		}
+
+		//Dependencies are named relative to the FSO root, which is the folder the mod sits in
+		let fso_root = mod_ini.parent().and_then(Path::parent).unwrap_or(Path::new(""));
+		for dependency in dependencies {
+			let tables_dir = mod_tables_dir(&fso_root.join(&dependency));
+			if tables_dir.is_dir() {
+				self.add_reference_directory(&tables_dir);
+			}
+			else {
+				self.notes.push((Note {
+					text: format!("Dependency {} has no tables directory at {}!", dependency, tables_dir.to_string_lossy()),
+					severity: NoteSeverity::Warning,
+					timeout: 5f32
+				}, None));
+			}
+		}
+	}
+}
+
+pub(crate) fn mod_tables_dir(mod_dir: &Path) -> PathBuf {
+	mod_dir.join("data").join("tables")
+}
+
+//Reads the primarylist and secondarylist of the [multimod] section, which look like "secondarylist = basemod, othermod;"
+pub(crate) fn mod_ini_dependencies(content: &str) -> Vec<String> {
+	let mut dependencies: Vec<String> = Vec::new();
+	let mut in_multimod = false;
+	for line in content.lines().map(str::trim) {
+		if line.starts_with('[') {
+			in_multimod = line.eq_ignore_ascii_case("[multimod]");
+			continue;
+		}
+		let Some((key, value)) = line.split_once('=') else { continue; };
+		let key = key.trim().to_ascii_lowercase();
+		if in_multimod && (key == "primarylist" || key == "secondarylist") {
+			let value = value.trim().trim_end_matches(';');
+			for dependency in value.split(',').map(str::trim).filter(|dependency| !dependency.is_empty()) {
+				if !dependencies.iter().any(|other| other == dependency) {
+					dependencies.push(dependency.to_string());
+				}
+			}
+		}
+	}
+	dependencies
+}
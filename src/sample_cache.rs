@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use fso_tables_impl::curves::{Curve, CurveTable};
+use crate::TableData;
+use crate::dependencies::{DependencyGraph, subcurves_of};
+use crate::history::curve_equal;
+use crate::plot_panel::{effective_curves, from_curve, sample_range};
+
+//Plotted points of every curve, so curves are only sampled again after they or one of their subcurves changed
+#[derive(Default)]
+pub(crate) struct SampleCache {
+	//The effective definition of every curve name as of the last refresh
+	effective: HashMap<String, Curve>,
+	//Bumped for a curve name whenever its effective definition or anything it uses as a subcurve changes
+	revisions: HashMap<String, u64>,
+	last_revision: u64,
+	samples: HashMap<(PathBuf, String), CurveSamples>
+}
+
+struct CurveSamples {
+	curve: Curve,
+	//The newest revision among the subcurves at the time of sampling
+	subcurve_revision: u64,
+	points: Vec<[f64; 2]>,
+	outside: Option<OutsideSamples>
+}
+
+//Samples before the first and after the last keyframe, for the input range they were taken for
+struct OutsideSamples {
+	domain: (f32, f32),
+	before: Vec<[f64; 2]>,
+	after: Vec<[f64; 2]>
+}
+
+impl SampleCache {
+	//Returns whether any curve changed since the last refresh
+	pub(crate) fn refresh(&mut self, tables: &[(CurveTable, TableData)]) -> bool {
+		let available_curves = effective_curves(tables.iter().flat_map(|(table, _)| table.curves.iter()));
+		let mut changed = available_curves.iter()
+			.filter(|curve| !self.effective.get(&curve.name).is_some_and(|old| curve_equal(old, curve)))
+			.map(|curve| curve.name.clone()).collect::<Vec<String>>();
+		changed.extend(self.effective.keys().filter(|name| !available_curves.iter().any(|curve| curve.name == **name)).cloned());
+		if changed.is_empty() {
+			return false;
+		}
+
+		self.last_revision += 1;
+		let dependencies = DependencyGraph::new(&available_curves);
+		for curve in available_curves.iter().filter(|curve| changed.iter().any(|name| dependencies.depends_on(&curve.name, name))) {
+			self.revisions.insert(curve.name.clone(), self.last_revision);
+		}
+		for name in changed {
+			self.revisions.insert(name, self.last_revision);
+		}
+		self.effective = available_curves.iter().map(|curve| (curve.name.clone(), (*curve).clone())).collect();
+		self.samples.retain(|(file, name), _| tables.iter().any(|(table, file_data)| file_data.file == *file && table.curves.iter().any(|curve| curve.name == *name)));
+		true
+	}
+
+	pub(crate) fn points(&mut self, file: &Path, curve: &Curve, available_curves: &Vec<&Curve>) -> Vec<[f64; 2]> {
+		self.entry(file, curve, available_curves).points.clone()
+	}
+
+	pub(crate) fn outside_points(&mut self, file: &Path, curve: &Curve, available_curves: &Vec<&Curve>, (start, end): (f32, f32)) -> (Vec<[f64; 2]>, Vec<[f64; 2]>) {
+		let entry = self.entry(file, curve, available_curves);
+		match &entry.outside {
+			Some(outside) if outside.domain == (start, end) => (outside.before.clone(), outside.after.clone()),
+			_ => {
+				let (first, last) = (curve.keyframes[0].pos.0, curve.keyframes[curve.keyframes.len() - 1].pos.0);
+				let before = sample_range(curve, available_curves, start, first);
+				let after = sample_range(curve, available_curves, last, end);
+				entry.outside = Some(OutsideSamples { domain: (start, end), before: before.clone(), after: after.clone() });
+				(before, after)
+			}
+		}
+	}
+
+	fn entry(&mut self, file: &Path, curve: &Curve, available_curves: &Vec<&Curve>) -> &mut CurveSamples {
+		let subcurve_revision = subcurves_of(curve).filter_map(|subcurve| self.revisions.get(subcurve)).copied().max().unwrap_or_default();
+		let key = (file.to_path_buf(), curve.name.clone());
+		let valid = self.samples.get(&key).is_some_and(|cached| cached.subcurve_revision == subcurve_revision && curve_equal(&cached.curve, curve));
+		if !valid {
+			self.samples.insert(key.clone(), CurveSamples { curve: curve.clone(), subcurve_revision, points: from_curve(curve, available_curves), outside: None });
+		}
+		self.samples.get_mut(&key).unwrap()
+	}
+}
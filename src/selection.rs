@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+use fso_tables_impl::curves::CurveTable;
+use crate::{CurvEdit, TableData};
+
+//Selected keyframes as (table, curve, keyframe). The last one is the keyframe shown in the keyframe panel.
+//These keep the selection pointing at the same keyframes when the indices around them shift.
+
+pub(crate) fn select_only(selection: &mut Vec<(usize, usize, usize)>, keyframe: (usize, usize, usize)) {
+	selection.clear();
+	selection.push(keyframe);
+}
+
+pub(crate) fn toggle_selected(selection: &mut Vec<(usize, usize, usize)>, keyframe: (usize, usize, usize)) {
+	if let Some(idx) = selection.iter().position(|selected| *selected == keyframe) {
+		selection.remove(idx);
+	}
+	else {
+		selection.push(keyframe);
+	}
+}
+
+pub(crate) fn make_primary(selection: &mut Vec<(usize, usize, usize)>, keyframe: (usize, usize, usize)) {
+	selection.retain(|selected| *selected != keyframe);
+	selection.push(keyframe);
+}
+
+pub(crate) fn selected_in_curve(selection: &[(usize, usize, usize)], (table, curve): (usize, usize)) -> Vec<usize> {
+	let mut keyframes = selection.iter().filter(|(sel_table, sel_curve, _)| *sel_table == table && *sel_curve == curve).map(|(_, _, keyframe)| *keyframe).collect::<Vec<usize>>();
+	keyframes.sort();
+	keyframes
+}
+
+pub(crate) fn keyframe_removed(selection: &mut Vec<(usize, usize, usize)>, (table, curve): (usize, usize), keyframe: usize) {
+	selection.retain(|selected| *selected != (table, curve, keyframe));
+	for (sel_table, sel_curve, sel_keyframe) in selection.iter_mut() {
+		if *sel_table == table && *sel_curve == curve && *sel_keyframe > keyframe {
+			*sel_keyframe -= 1;
+		}
+	}
+}
+
+pub(crate) fn keyframe_inserted(selection: &mut [(usize, usize, usize)], (table, curve): (usize, usize), keyframe: usize) {
+	for (sel_table, sel_curve, sel_keyframe) in selection.iter_mut() {
+		if *sel_table == table && *sel_curve == curve && *sel_keyframe >= keyframe {
+			*sel_keyframe += 1;
+		}
+	}
+}
+
+pub(crate) fn curve_cleared(selection: &mut Vec<(usize, usize, usize)>, (table, curve): (usize, usize)) {
+	selection.retain(|(sel_table, sel_curve, _)| *sel_table != table || *sel_curve != curve);
+}
+
+pub(crate) fn curve_removed(selection: &mut Vec<(usize, usize, usize)>, (table, curve): (usize, usize)) {
+	curve_cleared(selection, (table, curve));
+	for (sel_table, sel_curve, _) in selection.iter_mut() {
+		if *sel_table == table && *sel_curve > curve {
+			*sel_curve -= 1;
+		}
+	}
+}
+
+pub(crate) fn curves_swapped(selection: &mut [(usize, usize, usize)], table: usize, first: usize, second: usize) {
+	for (sel_table, sel_curve, _) in selection.iter_mut() {
+		if *sel_table == table && *sel_curve == first {
+			*sel_curve = second;
+		}
+		else if *sel_table == table && *sel_curve == second {
+			*sel_curve = first;
+		}
+	}
+}
+
+pub(crate) fn table_removed(selection: &mut Vec<(usize, usize, usize)>, table: usize) {
+	selection.retain(|(sel_table, _, _)| *sel_table != table);
+	for (sel_table, _, _) in selection.iter_mut() {
+		if *sel_table > table {
+			*sel_table -= 1;
+		}
+	}
+}
+
+impl CurvEdit {
+	//For changes that replace tables wholesale: Indices may shift, so shown curves and the selection are found again by file and curve name
+	pub(crate) fn preserving_selection(&mut self, change: impl FnOnce(&mut CurvEdit)) {
+		let name_of = |tables: &Vec<(CurveTable, TableData)>, (table, curve): (usize, usize)| -> (PathBuf, String) {
+			(tables[table].1.file.clone(), tables[table].0.curves[curve].name.clone())
+		};
+		let shown = self.curves_to_show.iter().map(|curve| name_of(&self.tables, *curve)).collect::<Vec<_>>();
+		let selected = self.selected_keyframes.iter().map(|(table, curve, keyframe)| (name_of(&self.tables, (*table, *curve)), *keyframe)).collect::<Vec<_>>();
+
+		change(self);
+
+		let index_of = |tables: &Vec<(CurveTable, TableData)>, (file, name): &(PathBuf, String)| -> Option<(usize, usize)> {
+			let table = tables.iter().position(|(_, file_data)| file_data.file == *file)?;
+			let curve = tables[table].0.curves.iter().position(|curve| curve.name == *name)?;
+			Some((table, curve))
+		};
+		self.curves_to_show = shown.iter().filter_map(|curve| index_of(&self.tables, curve)).collect();
+		self.selected_keyframes = selected.iter().filter_map(|(curve, keyframe)| {
+			let (table, curve) = index_of(&self.tables, curve)?;
+			(*keyframe < self.tables[table].0.curves[curve].keyframes.len()).then_some((table, curve, *keyframe))
+		}).collect();
+	}
+}
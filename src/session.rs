@@ -0,0 +1,224 @@
+use std::fs;
+use std::path::PathBuf;
+use egui::{Pos2, Vec2};
+use homedir::get_my_home;
+use fso_tables_impl::curves::CurveTable;
+use crate::{CurvEdit, TableData};
+use crate::curves_panel::{DisplayMode, Extrapolation, EXTRAPOLATION_MAX_MARGIN, SnapMode, SnapTargets};
+use crate::keyboard::NudgeSteps;
+use crate::note_bar::{Note, NoteSeverity};
+use crate::plot_panel::KEYFRAME_MIN_X_DISTANCE;
+use crate::recent::RecentEntry;
+
+const SESSION_FILE_NAME: &str = ".curvedit-session";
+
+//What was open when CurvEdit was last closed. Curves are remembered by file and name, as table indices depend on what could be loaded.
+#[derive(Default)]
+pub(crate) struct Session {
+	pub(crate) window_pos: Option<Pos2>,
+	pub(crate) window_size: Option<Vec2>,
+	//(file, read-only)
+	tables: Vec<(PathBuf, bool)>,
+	shown: Vec<(PathBuf, String)>,
+	selected: Vec<(PathBuf, String, usize)>,
+	snap_mode: SnapMode,
+	display_mode: DisplayMode,
+	modifier_panel_width: f32,
+	nudge_steps: NudgeSteps,
+	snap_targets: SnapTargets,
+	extrapolation: Extrapolation,
+	evaluate_at: Option<f32>,
+	recent: Vec<RecentEntry>
+}
+
+fn session_file() -> Option<PathBuf> {
+	get_my_home().ok().flatten().map(|home| home.join(SESSION_FILE_NAME))
+}
+
+//One entry per line, fields separated by tabs. Anything unreadable or out of range is ignored, a broken session file should never keep CurvEdit from starting.
+pub(crate) fn load_session() -> Session {
+	let mut session = Session::default();
+	let Some(content) = session_file().and_then(|file| fs::read_to_string(file).ok()) else { return session; };
+
+	for line in content.lines() {
+		let fields = line.split('\t').collect::<Vec<&str>>();
+		match fields.as_slice() {
+			["window_pos", x, y] => {
+				if let (Ok(x), Ok(y)) = (x.parse::<f32>(), y.parse::<f32>()) {
+					if !x.is_finite() || !y.is_finite() { continue; }
+					session.window_pos = Some(Pos2::new(x, y));
+				}
+			}
+			["window_size", width, height] => {
+				if let (Ok(width), Ok(height)) = (width.parse::<f32>(), height.parse::<f32>()) {
+					if !is_positive(width) || !is_positive(height) { continue; }
+					session.window_size = Some(Vec2::new(width, height));
+				}
+			}
+			["modifier_panel_width", width] => {
+				if let Ok(width) = width.parse::<f32>() {
+					if !is_positive(width) { continue; }
+					session.modifier_panel_width = width;
+				}
+			}
+			["nudge_steps", coarse, fine] => {
+				if let (Ok(coarse), Ok(fine)) = (coarse.parse(), fine.parse()) {
+					if !is_valid_step(coarse) || !is_valid_step(fine) { continue; }
+					session.nudge_steps = NudgeSteps { coarse, fine };
+				}
+			}
+			["grid_snap", enabled, step_x, step_y, follow_plot] => {
+				session.snap_targets.grid = *enabled == "on";
+				session.snap_targets.follow_plot = *follow_plot == "follow_plot";
+				if let (Ok(step_x), Ok(step_y)) = (step_x.parse(), step_y.parse()) {
+					if !is_valid_step(step_x) || !is_valid_step(step_y) { continue; }
+					session.snap_targets.step_x = step_x;
+					session.snap_targets.step_y = step_y;
+				}
+			}
+			["keyframe_snap", enabled] => { session.snap_targets.keyframes = *enabled == "on"; }
+			["extrapolation", enabled, margin] => {
+				session.extrapolation.enabled = *enabled == "on";
+				if let Ok(margin) = margin.parse::<f32>() {
+					if !(0f32..=EXTRAPOLATION_MAX_MARGIN).contains(&margin) { continue; }
+					session.extrapolation.margin = margin;
+				}
+			}
+			["input_range", file, curve, start, end] => {
+				if let (Ok(start), Ok(end)) = (start.parse::<f32>(), end.parse::<f32>()) {
+					if !start.is_finite() || !end.is_finite() || start > end { continue; }
+					session.extrapolation.fixed_ranges.insert((PathBuf::from(file), curve.to_string()), (start, end));
+				}
+			}
+			["evaluate_at", x] => { session.evaluate_at = x.parse().ok().filter(|x: &f32| x.is_finite()); }
+			["snap_mode", mode] => {
+				session.snap_mode = match *mode {
+					"SnapX" => SnapMode::SnapX,
+					"SnapY" => SnapMode::SnapY,
+					"SnapCurve" => SnapMode::SnapCurve,
+					_ => SnapMode::NoSnap
+				};
+			}
+			["display_mode", mode] => {
+				session.display_mode = match *mode {
+					"Overlay" => DisplayMode::Overlay,
+					"Grid" => DisplayMode::Grid,
+					_ => DisplayMode::Stacked
+				};
+			}
+			["table", read_only, file] => { session.tables.push((PathBuf::from(file), *read_only == "reference")); }
+			["recent", kind, pinned, path] => {
+				session.recent.push(RecentEntry { path: PathBuf::from(path), is_directory: *kind == "directory", pinned: *pinned == "pinned" });
+			}
+			["shown", file, curve] => { session.shown.push((PathBuf::from(file), curve.to_string())); }
+			["selected", file, curve, keyframe] => {
+				if let Ok(keyframe) = keyframe.parse() {
+					session.selected.push((PathBuf::from(file), curve.to_string(), keyframe));
+				}
+			}
+			_ => {}
+		}
+	}
+	session
+}
+
+fn is_positive(value: f32) -> bool {
+	value.is_finite() && value > 0f32
+}
+
+//Steps are held to the same minimum as their controls, a zero step would never move anything
+fn is_valid_step(step: f32) -> bool {
+	step.is_finite() && step >= KEYFRAME_MIN_X_DISTANCE
+}
+
+impl CurvEdit {
+	pub(crate) fn restore_session(&mut self, session: Session) {
+		self.snap_mode = session.snap_mode;
+		self.display_mode = session.display_mode;
+		self.modifier_panel_width = session.modifier_panel_width;
+		self.nudge_steps = session.nudge_steps;
+		self.snap_targets = session.snap_targets;
+		self.extrapolation = session.extrapolation;
+		self.evaluate_at = session.evaluate_at;
+		self.recent = session.recent;
+
+		let mut missing: Vec<String> = Vec::new();
+		for (file, read_only) in session.tables {
+			if file.is_file() {
+				self.open_table(file, read_only);
+			}
+			else {
+				missing.push(file.to_string_lossy().to_string());
+			}
+		}
+		if !missing.is_empty() {
+			self.notes.push((Note {
+				text: format!("Skipped tables that no longer exist: {}!", missing.join(", ")),
+				severity: NoteSeverity::Warning,
+				timeout: 8f32
+			}, None));
+		}
+
+		let index_of = |tables: &[(CurveTable, TableData)], file: &PathBuf, name: &str| -> Option<(usize, usize)> {
+			let table = tables.iter().position(|(_, file_data)| file_data.file == *file)?;
+			let curve = tables[table].0.curves.iter().position(|curve| curve.name == name)?;
+			Some((table, curve))
+		};
+		self.curves_to_show = session.shown.iter().filter_map(|(file, name)| index_of(&self.tables, file, name)).collect();
+		self.selected_keyframes = session.selected.iter().filter_map(|(file, name, keyframe)| {
+			let (table, curve) = index_of(&self.tables, file, name)?;
+			(*keyframe < self.tables[table].0.curves[curve].keyframes.len()).then_some((table, curve, *keyframe))
+		}).collect();
+	}
+
+	pub(crate) fn save_session(&self, ctx: &egui::Context) {
+		let Some(session_file) = session_file() else { return; };
+
+		let mut lines: Vec<String> = Vec::new();
+		let (outer_rect, inner_rect) = ctx.input(|i| (i.viewport().outer_rect, i.viewport().inner_rect));
+		if let Some(outer_rect) = outer_rect {
+			lines.push(format!("window_pos\t{}\t{}", outer_rect.min.x, outer_rect.min.y));
+		}
+		if let Some(inner_rect) = inner_rect {
+			lines.push(format!("window_size\t{}\t{}", inner_rect.width(), inner_rect.height()));
+		}
+		lines.push(format!("modifier_panel_width\t{}", self.modifier_panel_width));
+		lines.push(format!("nudge_steps\t{}\t{}", self.nudge_steps.coarse, self.nudge_steps.fine));
+		lines.push(format!("grid_snap\t{}\t{}\t{}\t{}", if self.snap_targets.grid { "on" } else { "off" }, self.snap_targets.step_x, self.snap_targets.step_y, if self.snap_targets.follow_plot { "follow_plot" } else { "fixed" }));
+		lines.push(format!("keyframe_snap\t{}", if self.snap_targets.keyframes { "on" } else { "off" }));
+		lines.push(format!("extrapolation\t{}\t{}", if self.extrapolation.enabled { "on" } else { "off" }, self.extrapolation.margin));
+		for ((file, curve), (start, end)) in self.extrapolation.fixed_ranges.iter() {
+			lines.push(format!("input_range\t{}\t{}\t{}\t{}", file.to_string_lossy(), curve, start, end));
+		}
+		if let Some(x) = self.evaluate_at {
+			lines.push(format!("evaluate_at\t{}", x));
+		}
+		lines.push(format!("snap_mode\t{}", match self.snap_mode {
+			SnapMode::NoSnap => "NoSnap",
+			SnapMode::SnapX => "SnapX",
+			SnapMode::SnapY => "SnapY",
+			SnapMode::SnapCurve => "SnapCurve"
+		}));
+		lines.push(format!("display_mode\t{}", match self.display_mode {
+			DisplayMode::Stacked => "Stacked",
+			DisplayMode::Overlay => "Overlay",
+			DisplayMode::Grid => "Grid"
+		}));
+		for entry in self.recent.iter() {
+			lines.push(format!("recent\t{}\t{}\t{}", if entry.is_directory { "directory" } else { "table" }, if entry.pinned { "pinned" } else { "unpinned" }, entry.path.to_string_lossy()));
+		}
+		//Tables from inside VP archives have no file of their own to reopen
+		for (_, file_data) in self.tables.iter().filter(|(_, file_data)| file_data.file.is_file()) {
+			lines.push(format!("table\t{}\t{}", if file_data.read_only { "reference" } else { "editable" }, file_data.file.to_string_lossy()));
+		}
+		for (table, curve) in self.curves_to_show.iter() {
+			lines.push(format!("shown\t{}\t{}", self.tables[*table].1.file.to_string_lossy(), self.tables[*table].0.curves[*curve].name));
+		}
+		for (table, curve, keyframe) in self.selected_keyframes.iter() {
+			lines.push(format!("selected\t{}\t{}\t{}", self.tables[*table].1.file.to_string_lossy(), self.tables[*table].0.curves[*curve].name, keyframe));
+		}
+
+		//Losing the session is not worth bothering anyone about on the way out
+		let _ = fs::write(session_file, lines.join("\n"));
+	}
+}
@@ -0,0 +1,224 @@
+use std::fs;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use egui::{Align2, Vec2};
+use native_dialog::FileDialog;
+use crate::{CurvEdit, TableData};
+use crate::context_bar::{is_curve_table, parse_table_text};
+use crate::note_bar::{Note, NoteSeverity};
+
+const VP_HEADER_SIZE: usize = 16;
+const VP_ENTRY_SIZE: usize = 44;
+const VP_NAME_SIZE: usize = 32;
+
+pub(crate) struct VpEntry {
+	//Path inside the archive, like data/tables/curves.tbl
+	path: String,
+	offset: usize,
+	size: usize
+}
+
+pub(crate) struct VpDialog {
+	archive: PathBuf,
+	tables: Vec<VpEntry>
+}
+
+enum VpAction {
+	Open(usize),
+	Extract(usize)
+}
+
+//Reads the directory of a VP archive and returns all files in it. Directories are flattened into the file paths.
+//Archives can be gigabytes large, so only the header and the directory at the end are read.
+pub(crate) fn read_vp_directory(archive: &Path) -> Result<Vec<VpEntry>, String> {
+	let mut file = File::open(archive).map_err(|error| error.to_string())?;
+	let archive_size = file.metadata().map_err(|error| error.to_string())?.len();
+	let to_usize = |bytes: &[u8]| -> Result<usize, String> {
+		usize::try_from(i32::from_le_bytes(bytes.try_into().unwrap())).map_err(|_| "Negative offset or size in archive".to_string())
+	};
+
+	let mut header = [0u8; VP_HEADER_SIZE];
+	file.read_exact(&mut header).map_err(|_| "Not a VP archive".to_string())?;
+	if &header[0..4] != b"VPVP" {
+		return Err("Not a VP archive".to_string());
+	}
+	let dir_offset = to_usize(&header[8..12])?;
+	let num_entries = to_usize(&header[12..16])?;
+	let dir_size = num_entries.checked_mul(VP_ENTRY_SIZE).ok_or("Unexpected end of archive")?;
+	if (dir_offset as u64).saturating_add(dir_size as u64) > archive_size {
+		return Err("Unexpected end of archive".to_string());
+	}
+
+	let mut directory = vec![0u8; dir_size];
+	file.seek(SeekFrom::Start(dir_offset as u64)).and_then(|_| file.read_exact(&mut directory)).map_err(|error| error.to_string())?;
+
+	let mut entries: Vec<VpEntry> = Vec::new();
+	let mut directories: Vec<String> = Vec::new();
+	for entry in directory.chunks_exact(VP_ENTRY_SIZE) {
+		let offset = to_usize(&entry[0..4])?;
+		let size = to_usize(&entry[4..8])?;
+		let name = &entry[8..8 + VP_NAME_SIZE];
+		let name = String::from_utf8_lossy(name.split(|byte| *byte == 0).next().unwrap_or_default()).to_string();
+		let timestamp = to_usize(&entry[8 + VP_NAME_SIZE..VP_ENTRY_SIZE])?;
+
+		//Directory entries are the only ones without size and timestamp, ".." leaves the current one
+		if size == 0 && timestamp == 0 {
+			if name == ".." {
+				directories.pop();
+			}
+			else {
+				directories.push(name);
+			}
+			continue;
+		}
+
+		if (offset as u64).saturating_add(size as u64) > archive_size {
+			return Err(format!("Entry {} lies outside of the archive", name));
+		}
+		let mut path = directories.join("/");
+		if !path.is_empty() {
+			path.push('/');
+		}
+		path.push_str(&name);
+		entries.push(VpEntry { path, offset, size });
+	}
+	Ok(entries)
+}
+
+//Reads just the one file out of the archive
+fn read_vp_entry(archive: &Path, entry: &VpEntry) -> Result<Vec<u8>, String> {
+	let mut file = File::open(archive).map_err(|error| error.to_string())?;
+	let mut content = vec![0u8; entry.size];
+	file.seek(SeekFrom::Start(entry.offset as u64)).and_then(|_| file.read_exact(&mut content)).map_err(|_| "Entry lies outside of the archive".to_string())?;
+	Ok(content)
+}
+
+impl CurvEdit {
+	pub(crate) fn open_vp_archive(&mut self, archive: PathBuf) {
+		let directory = read_vp_directory(&archive);
+		match directory {
+			Ok(entries) => {
+				let tables = entries.into_iter().filter(|entry| is_curve_table(Path::new(&entry.path))).collect::<Vec<VpEntry>>();
+				if tables.is_empty() {
+					self.notes.push((Note {
+						text: format!("{} does not contain any curve tables!", archive.file_name().unwrap_or("".as_ref()).to_string_lossy()),
+						severity: NoteSeverity::Warning,
+						timeout: 5f32
+					}, None));
+				}
+				else {
+					self.vp_dialog = Some(VpDialog { archive, tables });
+				}
+			}
+			Err(error) => {
+				self.notes.push((Note {
+					text: format!("Cannot read archive {}: {}!", archive.file_name().unwrap_or("".as_ref()).to_string_lossy(), error),
+					severity: NoteSeverity::Error,
+					timeout: 5f32
+				}, None));
+			}
+		}
+	}
+
+	pub(crate) fn vp_archive_dialog(&mut self, ctx: &egui::Context) {
+		let Some(dialog) = &self.vp_dialog else { return; };
+
+		let mut action: Option<VpAction> = None;
+		let mut open = true;
+		egui::Window::new(format!("Curve tables in {}", dialog.archive.file_name().unwrap_or("".as_ref()).to_string_lossy()))
+			.collapsible(false)
+			.resizable(false)
+			.open(&mut open)
+			.anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+			.show(ctx, |ui| {
+				egui::ScrollArea::vertical().max_height(300f32).show(ui, |ui| {
+					for (entry_num, entry) in dialog.tables.iter().enumerate() {
+						ui.horizontal(|ui| {
+							ui.label(&entry.path);
+							let virtual_path = dialog.archive.join(&entry.path);
+							let is_open = self.tables.iter().any(|(_, file_data)| file_data.file == virtual_path);
+							if ui.add_enabled(!is_open, egui::Button::new("Open read-only")).on_hover_text("Load the table as a read-only reference.").clicked() {
+								action = Some(VpAction::Open(entry_num));
+							}
+							if ui.button("Extract copy").on_hover_text("Save the table as a loose file and open it for editing.").clicked() {
+								action = Some(VpAction::Extract(entry_num));
+							}
+						});
+					}
+				});
+			});
+
+		if !open {
+			self.vp_dialog = None;
+			return;
+		}
+		let Some(action) = action else { return; };
+		let Some(dialog) = &self.vp_dialog else { return; };
+		let entry_num = match action { VpAction::Open(entry_num) | VpAction::Extract(entry_num) => entry_num };
+		let entry = &dialog.tables[entry_num];
+		let archive_name = dialog.archive.file_name().unwrap_or("".as_ref()).to_string_lossy().to_string();
+		let virtual_path = dialog.archive.join(&entry.path);
+		let file_name = Path::new(&entry.path).file_name().unwrap_or("curves.tbl".as_ref()).to_string_lossy().to_string();
+
+		//The archive is read again, it may have changed since the directory was listed
+		let content = read_vp_entry(&dialog.archive, entry);
+		let content = match content {
+			Ok(content) => content,
+			Err(error) => {
+				self.notes.push((Note {
+					text: format!("Cannot read {} from {}: {}!", entry.path, archive_name, error),
+					severity: NoteSeverity::Error,
+					timeout: 5f32
+				}, None));
+				return;
+			}
+		};
+
+		match action {
+			VpAction::Open(_) => {
+				match parse_table_text(&content, &format!("vp-{}", file_name)) {
+					Ok(table) => {
+						match self.add_table(table, TableData { file: virtual_path.clone(), read_only: true, ..Default::default() }) {
+							Ok(_) => { self.report_overrides(&virtual_path); }
+							Err(error) => {
+								self.notes.push((Note {
+									text: format!("{}!", error),
+									severity: NoteSeverity::Error,
+									timeout: 5f32
+								}, None));
+							}
+						}
+					}
+					Err(error) => {
+						self.notes.push((Note {
+							text: format!("Failed to parse {} in {} at {}!", entry.path, archive_name, error),
+							severity: NoteSeverity::Error,
+							timeout: 5f32
+						}, None));
+					}
+				}
+			}
+			VpAction::Extract(_) => {
+				let path = FileDialog::new()
+					.set_location(dialog.archive.parent().unwrap_or(&self.default_path))
+					.set_filename(&file_name)
+					.add_filter("FSO Table", &["tbl", "tbm"])
+					.show_save_single_file();
+				let Ok(Some(path)) = path else { return; };
+				match fs::write(&path, &content) {
+					Ok(_) => {
+						self.open_table(path, false);
+					}
+					Err(error) => {
+						self.notes.push((Note {
+							text: format!("Cannot save table {}: {}!", path.to_string_lossy(), error),
+							severity: NoteSeverity::Error,
+							timeout: 5f32
+						}, None));
+					}
+				}
+			}
+		}
+	}
+}